@@ -0,0 +1,7 @@
+// Files shipped inside the executable, so it works no matter which folder it's started from
+
+/// Window and tray icon
+pub const ICON: &[u8] = include_bytes!("../assets/icon.ico");
+
+/// Icon of the About button
+pub const INFO_ICON: &[u8] = include_bytes!("../assets/info-square-rounded.png");
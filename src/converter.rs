@@ -0,0 +1,50 @@
+use crate::ffmpeg::JobOptions;
+use crate::pipeline::{self, Conversion, ProgressFn};
+use crate::probe::MediaInfo;
+use crate::settings::Settings;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+/// A running conversion, boxed so backends can be picked at runtime
+pub type ConversionFuture<'a> = Pin<Box<dyn Future<Output = Result<Conversion, String>> + Send + 'a>>;
+
+/// Turns one input into the output files of `settings`, whichever way it's done
+pub trait Converter: Send + Sync {
+    fn convert<'a>(
+        &'a self,
+        input: &'a Path,
+        output: &'a Path,
+        settings: &'a Settings,
+        job: JobOptions,
+        media: Option<&'a MediaInfo>,
+        on_progress: &'a ProgressFn<'a>,
+    ) -> ConversionFuture<'a>;
+}
+
+/// Runs the ffmpeg program, supports every option
+pub struct FfmpegCli;
+
+impl Converter for FfmpegCli {
+    fn convert<'a>(
+        &'a self,
+        input: &'a Path,
+        output: &'a Path,
+        settings: &'a Settings,
+        job: JobOptions,
+        media: Option<&'a MediaInfo>,
+        on_progress: &'a ProgressFn<'a>,
+    ) -> ConversionFuture<'a> {
+        Box::pin(pipeline::run(input, output, settings, job, media, on_progress))
+    }
+}
+
+/// Backend picked in `settings`, the ffmpeg program unless the app was built with the `native` feature
+pub fn for_settings(settings: &Settings) -> &'static dyn Converter {
+    #[cfg(feature = "native")]
+    if settings.native_backend {
+        return &crate::native::Native;
+    }
+    let _ = settings;
+    &FfmpegCli
+}
@@ -0,0 +1,39 @@
+/// A track in a cue sheet
+pub struct CueTrack {
+    /// Start time in the audio file, in seconds
+    pub start: f64,
+    pub title: Option<String>,
+}
+
+/// Build a cue sheet describing `tracks` inside the audio file `file_name`
+///
+/// `file_type` is the cue `FILE` type, e.g. `MP3` or `WAVE`.
+pub fn cue_sheet(file_name: &str, file_type: &str, title: &str, performer: &str, tracks: &[CueTrack]) -> String {
+    let mut sheet = String::new();
+    if !performer.is_empty() {
+        sheet.push_str(&format!("PERFORMER \"{}\"\n", escape(performer)));
+    }
+    if !title.is_empty() {
+        sheet.push_str(&format!("TITLE \"{}\"\n", escape(title)));
+    }
+    sheet.push_str(&format!("FILE \"{}\" {}\n", escape(file_name), file_type));
+
+    for (n, track) in tracks.iter().enumerate() {
+        sheet.push_str(&format!("  TRACK {:02} AUDIO\n", n + 1));
+        let title = track.title.clone().unwrap_or_else(|| format!("Track {}", n + 1));
+        sheet.push_str(&format!("    TITLE \"{}\"\n", escape(&title)));
+        sheet.push_str(&format!("    INDEX 01 {}\n", index_time(track.start)));
+    }
+    sheet
+}
+
+/// Cue timestamp `mm:ss:ff`, with 75 frames per second
+fn index_time(seconds: f64) -> String {
+    let frames = (seconds.max(0.0) * 75.0).round() as u64;
+    format!("{:02}:{:02}:{:02}", frames / 75 / 60, frames / 75 % 60, frames % 75)
+}
+
+/// Cue sheets have no escape sequences, so swap double quotes for single ones
+fn escape(text: &str) -> String {
+    text.replace('"', "'")
+}
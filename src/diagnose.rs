@@ -0,0 +1,77 @@
+/// A known kind of failure, explained for people who don't read FFmpeg output
+pub struct Diagnosis {
+    pub message: &'static str,
+    /// What the user can try next
+    pub fix: &'static str,
+}
+
+/// Known failures, matched case-insensitively against the error text, first match wins
+///
+/// Disk and permission errors come first: they also mention the file, which would otherwise look like a missing input.
+static KNOWN: [(&[&str], Diagnosis); 7] = [
+    (
+        &["could not start ffmpeg", "could not start ffprobe"],
+        Diagnosis {
+            message: "FFmpeg is not installed",
+            fix: "Install FFmpeg and make sure it's on your PATH, then restart the app",
+        },
+    ),
+    (
+        &["ffmpeg stalled"],
+        Diagnosis {
+            message: "FFmpeg stopped responding",
+            fix: "The file may be damaged, or on a slow network drive; retry, or raise the stall timeout in the advanced options",
+        },
+    ),
+    (
+        &["no space left on device", "not enough space on the disk", "disk full"],
+        Diagnosis {
+            message: "The disk is full",
+            fix: "Free up some space or choose an output folder on another drive",
+        },
+    ),
+    (
+        &["permission denied", "access is denied", "operation not permitted"],
+        Diagnosis {
+            message: "Permission denied",
+            fix: "Choose an output folder you can write to, and close programs that have the file open",
+        },
+    ),
+    (
+        &["no such file or directory", "cannot find the file", "cannot find the path"],
+        Diagnosis {
+            message: "File not found",
+            fix: "Check that the file hasn't been moved, renamed or deleted",
+        },
+    ),
+    (
+        &["does not contain any stream", "matches no streams", "output file is empty, nothing was encoded"],
+        Diagnosis {
+            message: "The file has no audio",
+            fix: "Make sure the video has a sound track, some screen recordings and GIF conversions don't",
+        },
+    ),
+    (
+        &[
+            "unknown encoder",
+            "decoder not found",
+            "unsupported codec",
+            "could not find codec parameters",
+            "invalid data found when processing input",
+            "moov atom not found",
+        ],
+        Diagnosis {
+            message: "Unsupported or damaged file",
+            fix: "The file may be incomplete, or use a codec your FFmpeg build doesn't include; try a full FFmpeg build",
+        },
+    ),
+];
+
+/// Recognize a common failure in `error`, `None` when only the raw output can help
+pub fn diagnose(error: &str) -> Option<&'static Diagnosis> {
+    let error = error.to_lowercase();
+    KNOWN
+        .iter()
+        .find(|(patterns, _)| patterns.iter().any(|pattern| error.contains(pattern)))
+        .map(|(_, diagnosis)| diagnosis)
+}
@@ -0,0 +1,178 @@
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Release with static FFmpeg builds and a `checksums.sha256` list
+const RELEASE_URL: &str = "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest";
+
+/// Where a managed FFmpeg download has got to
+#[derive(Clone, PartialEq)]
+pub enum DownloadState {
+    Idle,
+    /// Fetching the archive, 0.0 to 1.0
+    Downloading(f32),
+    Extracting,
+    Done,
+    Failed(String),
+}
+
+/// Archive with a static build for this platform, `None` where there is none to offer
+fn archive_name() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("windows", "x86_64") => Some("ffmpeg-master-latest-win64-gpl.zip"),
+        ("windows", "aarch64") => Some("ffmpeg-master-latest-winarm64-gpl.zip"),
+        ("linux", "x86_64") => Some("ffmpeg-master-latest-linux64-gpl.tar.xz"),
+        ("linux", "aarch64") => Some("ffmpeg-master-latest-linuxarm64-gpl.tar.xz"),
+        _ => None,
+    }
+}
+
+/// Whether a build can be downloaded for this platform
+pub fn available() -> bool {
+    archive_name().is_some()
+}
+
+/// Folder downloaded binaries are kept in
+fn install_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "vid2mp3").map(|dirs| dirs.data_dir().join("ffmpeg"))
+}
+
+/// FFmpeg and ffprobe from an earlier download, when both are there
+pub fn installed() -> Option<(PathBuf, PathBuf)> {
+    let dir = install_dir()?;
+    let ffmpeg = dir.join(format!("ffmpeg{}", std::env::consts::EXE_SUFFIX));
+    let ffprobe = dir.join(format!("ffprobe{}", std::env::consts::EXE_SUFFIX));
+    (ffmpeg.is_file() && ffprobe.is_file()).then_some((ffmpeg, ffprobe))
+}
+
+/// Download, verify and unpack FFmpeg on a background thread, reporting through `state`
+pub fn start(state: Arc<Mutex<DownloadState>>) {
+    *state.lock().unwrap() = DownloadState::Downloading(0.0);
+    std::thread::spawn(move || {
+        let result = install(&state);
+        if let Err(ref e) = result {
+            println!("FFmpeg download failed: {}", e);
+        }
+        *state.lock().unwrap() = match result {
+            Ok(()) => DownloadState::Done,
+            Err(e) => DownloadState::Failed(e),
+        };
+    });
+}
+
+fn install(state: &Mutex<DownloadState>) -> Result<(), String> {
+    let name = archive_name().ok_or("No FFmpeg download is available for this platform")?;
+    let dir = install_dir().ok_or("Could not find the app's data folder")?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    // The checksum list comes first, so a tampered or truncated archive is never unpacked
+    let checksums = ureq::get(format!("{}/checksums.sha256", RELEASE_URL))
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| format!("Could not fetch checksums: {}", e))?;
+    let expected = checksums
+        .lines()
+        .find_map(|line| {
+            let (hash, file) = line.split_once(char::is_whitespace)?;
+            (file.trim().trim_start_matches('*') == name).then(|| hash.to_lowercase())
+        })
+        .ok_or_else(|| format!("No checksum published for {}", name))?;
+
+    let archive = dir.join(name);
+    println!("Downloading {}", name);
+    let mut hasher = Sha256::new();
+    fetch(&format!("{}/{}", RELEASE_URL, name), &archive, state, |chunk| hasher.update(chunk))?;
+
+    let actual: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    if actual != expected {
+        let _ = fs::remove_file(&archive);
+        return Err("The download is corrupted, its checksum doesn't match".to_string());
+    }
+
+    *state.lock().unwrap() = DownloadState::Extracting;
+    let result = extract(&archive, &dir);
+    let _ = fs::remove_file(&archive);
+    result?;
+    println!("FFmpeg installed to '{}'", dir.display());
+    Ok(())
+}
+
+/// Download `url` to `target`, reporting progress through `state` and handing every chunk to `on_chunk`
+///
+/// The file is written as `target.part` and renamed once complete, so a failed download never
+/// leaves a truncated file under the real name. The `.part` file is removed when it fails.
+pub fn fetch(url: &str, target: &Path, state: &Mutex<DownloadState>, mut on_chunk: impl FnMut(&[u8])) -> Result<(), String> {
+    let part = crate::pipeline::part_path(target);
+    let result = (|| {
+        let mut response = ureq::get(url).call().map_err(|e| format!("Download failed: {}", e))?;
+        let total = response.body().content_length();
+        let mut reader = response.body_mut().as_reader();
+        let mut file = File::create(&part).map_err(|e| e.to_string())?;
+        let mut buffer = vec![0; 64 * 1024];
+        let mut received = 0u64;
+        loop {
+            let read = reader.read(&mut buffer).map_err(|e| format!("Download failed: {}", e))?;
+            if read == 0 {
+                break;
+            }
+            on_chunk(&buffer[..read]);
+            file.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+            received += read as u64;
+            if let Some(total) = total {
+                *state.lock().unwrap() = DownloadState::Downloading(received as f32 / total as f32);
+            }
+        }
+        file.sync_all().map_err(|e| e.to_string())?;
+        drop(file);
+        fs::rename(&part, target).map_err(|e| e.to_string())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+/// Copy `ffmpeg` and `ffprobe` out of the archive's `bin` folder into `dir`
+fn extract(archive: &Path, dir: &Path) -> Result<(), String> {
+    let wanted = |path: &Path| {
+        let name = path.file_name()?.to_str()?;
+        let is_tool = [format!("ffmpeg{}", std::env::consts::EXE_SUFFIX), format!("ffprobe{}", std::env::consts::EXE_SUFFIX)]
+            .contains(&name.to_string());
+        (is_tool && path.parent()?.ends_with("bin")).then(|| dir.join(name))
+    };
+
+    let file = File::open(archive).map_err(|e| e.to_string())?;
+    if archive.extension().is_some_and(|ext| ext == "zip") {
+        let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).map_err(|e| e.to_string())?;
+            if let Some(target) = entry.enclosed_name().and_then(|path| wanted(&path)) {
+                write_tool(&mut entry, &target)?;
+            }
+        }
+    } else {
+        let mut tar = tar::Archive::new(xz2::read::XzDecoder::new(file));
+        for entry in tar.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            let target = entry.path().ok().and_then(|path| wanted(&path));
+            if let Some(target) = target {
+                write_tool(&mut entry, &target)?;
+            }
+        }
+    }
+    installed().map(|_| ()).ok_or_else(|| "The archive didn't contain FFmpeg and ffprobe".to_string())
+}
+
+/// Write an executable to `target`, replacing an older download
+fn write_tool(reader: &mut impl Read, target: &Path) -> Result<(), String> {
+    let mut file = File::create(target).map_err(|e| e.to_string())?;
+    io::copy(reader, &mut file).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(0o755)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
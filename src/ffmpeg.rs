@@ -0,0 +1,900 @@
+use crate::download;
+use crate::probe::MediaInfo;
+use crate::settings::{Channels, Downmix, EncodeMode, OutputFormat, Settings, SplitMode};
+use crate::tags::Tags;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::process::Command;
+
+/// Peak ceiling used for loudness normalization (dBTP)
+const LOUDNORM_TRUE_PEAK: f32 = -1.5;
+/// Loudness range target used for loudness normalization (LU)
+const LOUDNORM_RANGE: f32 = 11.0;
+/// Level below which audio counts as silence when splitting (dB)
+const SILENCE_THRESHOLD_DB: i32 = -40;
+
+/// Named equalizer curves
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum EqPreset {
+    #[default]
+    Flat,
+    /// Cuts rumble and lifts the presence range for speech
+    VoiceBoost,
+    /// Removes low-frequency hum and rumble
+    BassCut,
+    BassBoost,
+    /// Brightens dull recordings
+    TrebleBoost,
+}
+
+impl EqPreset {
+    pub const ALL: [EqPreset; 5] = [
+        EqPreset::Flat,
+        EqPreset::VoiceBoost,
+        EqPreset::BassCut,
+        EqPreset::BassBoost,
+        EqPreset::TrebleBoost,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EqPreset::Flat => "Flat",
+            EqPreset::VoiceBoost => "Voice boost",
+            EqPreset::BassCut => "Bass cut",
+            EqPreset::BassBoost => "Bass boost",
+            EqPreset::TrebleBoost => "Treble boost",
+        }
+    }
+
+    /// FFmpeg filters implementing this curve
+    fn filters(self) -> Vec<String> {
+        let filters: &[&str] = match self {
+            EqPreset::Flat => &[],
+            EqPreset::VoiceBoost => &[
+                "highpass=f=100",
+                "equalizer=f=3000:t=o:w=1.5:g=4",
+                "lowpass=f=12000",
+            ],
+            EqPreset::BassCut => &["highpass=f=150"],
+            EqPreset::BassBoost => &["equalizer=f=80:t=o:w=1.5:g=5"],
+            EqPreset::TrebleBoost => &["equalizer=f=8000:t=o:w=2:g=4"],
+        };
+        filters.iter().map(|f| f.to_string()).collect()
+    }
+}
+
+/// Per-conversion options that aren't persisted between sessions
+#[derive(Clone, Default)]
+pub struct JobOptions {
+    /// Start of the range to convert, in seconds
+    pub trim_start: Option<f64>,
+    /// End of the range to convert, in seconds
+    pub trim_end: Option<f64>,
+    /// Audio stream to convert (`-map 0:a:N`), FFmpeg's default pick when `None`
+    pub audio_track: Option<usize>,
+    /// Image to embed as cover art
+    pub cover: Option<PathBuf>,
+    /// Loudness measured by the first normalization pass
+    pub loudness: Option<Loudness>,
+    /// Output timestamps to split at, in seconds
+    pub split_points: Vec<f64>,
+    /// Where the segment muxer lists the files it wrote, when splitting
+    pub segment_list: Option<PathBuf>,
+    /// ID3 tags to write
+    pub tags: Tags,
+    /// FFMETADATA file with generated chapters
+    pub chapters_file: Option<PathBuf>,
+    /// User-supplied arguments, already tokenized
+    pub extra_args: Vec<String>,
+    /// Shift the audio later (positive) or earlier (negative), in milliseconds
+    pub audio_offset_ms: i32,
+    pub eq: EqPreset,
+}
+
+/// Loudness statistics printed by the `loudnorm` filter's measurement pass
+#[derive(Clone, Deserialize)]
+pub struct Loudness {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+impl Loudness {
+    /// Extract the JSON block `loudnorm` prints at the end of FFmpeg's stderr
+    pub fn parse(stderr: &str) -> Option<Self> {
+        let start = stderr.rfind('{')?;
+        let end = start + stderr[start..].find('}')?;
+        serde_json::from_str(&stderr[start..=end]).ok()
+    }
+}
+
+/// Track gain and peak reported by the `replaygain` filter
+pub struct ReplayGain {
+    /// Gain in dB
+    pub gain: f64,
+    /// Peak sample amplitude, 1.0 = full scale
+    pub peak: f64,
+}
+
+impl ReplayGain {
+    /// Read `track_gain = -6.57 dB` and `track_peak = 0.977` from FFmpeg's stderr
+    pub fn parse(stderr: &str) -> Option<Self> {
+        let value_after = |key: &str| -> Option<f64> {
+            let line = stderr.lines().rev().find(|line| line.contains(key))?;
+            let rest = &line[line.find(key)? + key.len()..];
+            rest.split_whitespace().next()?.parse().ok()
+        };
+        Some(Self {
+            gain: value_after("track_gain = ")?,
+            peak: value_after("track_peak = ")?,
+        })
+    }
+}
+
+/// Find split points in `silencedetect` output: the middle of every detected gap
+pub fn parse_silence(stderr: &str) -> Vec<f64> {
+    let value_after = |line: &str, key: &str| -> Option<f64> {
+        let rest = &line[line.find(key)? + key.len()..];
+        rest.split_whitespace().next()?.parse().ok()
+    };
+
+    let mut points = Vec::new();
+    let mut silence_start = None;
+    for line in stderr.lines() {
+        if let Some(start) = value_after(line, "silence_start: ") {
+            silence_start = Some(start);
+        } else if let Some(end) = value_after(line, "silence_end: ")
+            && let Some(start) = silence_start.take()
+            && start > 0.0
+        {
+            points.push((start + end) / 2.0);
+        }
+    }
+    points
+}
+
+/// Segment muxer pattern for numbered parts of `output`, with literal `%` escaped
+fn segment_pattern(output: &Path) -> OsString {
+    // Names that aren't valid Unicode are passed on as they are, they can't be searched for `%`
+    let escape = |s: &OsStr| s.to_str().map_or_else(|| s.to_os_string(), |s| s.replace('%', "%%").into());
+    let mut name = output.file_stem().map(escape).unwrap_or_default();
+    name.push(" %02d.");
+    name.push(output.extension().map(escape).unwrap_or_default());
+    let dir = output.parent().map(|p| escape(p.as_os_str())).unwrap_or_default();
+    path_arg(&Path::new(&dir).join(name))
+}
+
+/// `args` quoted for display, anything that isn't UTF-8 shown lossily
+pub fn command_line(args: &[OsString]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+    shlex::try_join(args.iter().map(String::as_str)).unwrap_or_else(|_| args.join(" "))
+}
+
+/// Windows' classic path length limit, longer paths need the `\\?\` prefix
+const MAX_PATH: usize = 260;
+
+/// `path` as an FFmpeg argument, without going through UTF-8
+///
+/// On Windows, long absolute paths get the `\\?\` prefix that lifts the length limit.
+pub fn path_arg(path: &Path) -> OsString {
+    if cfg!(windows) {
+        extended_length(path).into_os_string()
+    } else {
+        path.as_os_str().to_os_string()
+    }
+}
+
+/// `path` in the `\\?\C:\...` or `\\?\UNC\server\...` form when it's too long for MAX_PATH
+fn extended_length(path: &Path) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    if text.len() < MAX_PATH || text.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+    // The prefix turns off slash conversion, so the path has to use backslashes already
+    let text = text.replace('/', r"\");
+    let has_drive = text.as_bytes().get(1) == Some(&b':') && text.as_bytes().get(2) == Some(&b'\\');
+    if let Some(share) = text.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", share))
+    } else if has_drive {
+        PathBuf::from(format!(r"\\?\{}", text))
+    } else {
+        // Relative paths can't take the prefix
+        path.to_path_buf()
+    }
+}
+
+/// FFmpeg and ffprobe binaries found by `set_paths`, `None` runs them from PATH
+static TOOL_PATHS: Mutex<(Option<PathBuf>, Option<PathBuf>)> = Mutex::new((None, None));
+
+/// Run FFmpeg and ffprobe from these paths, empty ones are looked up
+///
+/// FFmpeg is searched for in this order: the configured path, next to the app's executable,
+/// a downloaded build, then PATH. Without an ffprobe path, the one next to FFmpeg is used when it exists.
+pub fn set_paths(ffmpeg: &str, ffprobe: &str) {
+    let configured = |path: &str| (!path.trim().is_empty()).then(|| PathBuf::from(path.trim()));
+    let ffmpeg = configured(ffmpeg)
+        .or_else(bundled)
+        .or_else(|| download::installed().map(|(ffmpeg, _)| ffmpeg));
+    let ffprobe = configured(ffprobe).or_else(|| {
+        let sibling = ffmpeg
+            .as_ref()?
+            .with_file_name(format!("ffprobe{}", std::env::consts::EXE_SUFFIX));
+        sibling.is_file().then_some(sibling)
+    });
+    *TOOL_PATHS.lock().unwrap() = (ffmpeg, ffprobe);
+}
+
+/// FFmpeg shipped in the same folder as the app
+fn bundled() -> Option<PathBuf> {
+    let path = std::env::current_exe()
+        .ok()?
+        .with_file_name(format!("ffmpeg{}", std::env::consts::EXE_SUFFIX));
+    path.is_file().then_some(path)
+}
+
+/// Whether FFmpeg can be started at all
+pub async fn is_installed() -> bool {
+    command().arg("-version").output().await.is_ok_and(|output| output.status.success())
+}
+
+/// Names of the encoders this FFmpeg build has, `None` when they couldn't be listed
+pub async fn encoders() -> Option<HashSet<String>> {
+    let output = command().args(["-hide_banner", "-encoders"]).output().await.ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    // Entries like ` A....D libmp3lame  libmp3lame MP3 (MPEG audio layer 3)` follow a ` ------` line
+    let encoders = text
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect::<HashSet<_>>();
+    (!encoders.is_empty()).then_some(encoders)
+}
+
+/// Create an FFmpeg command that doesn't flash a console window on Windows
+pub fn command() -> Command {
+    let path = TOOL_PATHS.lock().unwrap().0.clone();
+    tool_command(path.as_deref().map_or(OsStr::new("ffmpeg"), Path::as_os_str))
+}
+
+/// Create an ffprobe command that doesn't flash a console window on Windows
+pub fn probe_command() -> Command {
+    let path = TOOL_PATHS.lock().unwrap().1.clone();
+    tool_command(path.as_deref().map_or(OsStr::new("ffprobe"), Path::as_os_str))
+}
+
+tokio::task_local! {
+    /// Set around FFmpeg runs that should leave the machine usable, on by default for conversions and thumbnails
+    pub static LOW_PRIORITY: bool;
+}
+
+fn tool_command(program: &OsStr) -> Command {
+    let low_priority = LOW_PRIORITY.try_with(|low| *low).unwrap_or(false);
+
+    // `nice` runs the tool in its own process, so killing it still stops the tool
+    #[cfg(unix)]
+    let mut cmd = if low_priority {
+        let mut cmd = Command::new("nice");
+        cmd.args(["-n", "10"]).arg(program);
+        cmd
+    } else {
+        Command::new(program)
+    };
+    #[cfg(not(unix))]
+    let mut cmd = Command::new(program);
+
+    // Dropping a cancelled conversion stops the process with it
+    cmd.kill_on_drop(true);
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x00004000;
+        let priority = if low_priority { BELOW_NORMAL_PRIORITY_CLASS } else { 0 };
+        cmd.creation_flags(CREATE_NO_WINDOW | priority);
+    }
+    cmd
+}
+
+/// Input options shared by every pass over `input`
+fn input_args(input: &Path, settings: &Settings, job: &JobOptions) -> Vec<OsString> {
+    let mut args = hwaccel_args(settings);
+
+    // Input seeking keeps trimming fast on long videos
+    if let Some(start) = job.trim_start {
+        args.push("-ss".into());
+        args.push(start.to_string().into());
+    }
+    if let Some(end) = job.trim_end {
+        args.push("-to".into());
+        args.push(end.to_string().into());
+    }
+
+    args.push("-i".into());
+    args.push(path_arg(input));
+    args
+}
+
+/// Decoder options that go before `-i`, picking the platform's hardware decoder when enabled
+pub fn hwaccel_args(settings: &Settings) -> Vec<OsString> {
+    if settings.hardware_decode {
+        vec!["-hwaccel".into(), "auto".into()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Stream selection for audio-only output
+fn audio_map_args(job: &JobOptions) -> Vec<OsString> {
+    let mut args = Vec::new();
+    if let Some(track) = job.audio_track {
+        args.push("-map".into());
+        args.push(format!("0:a:{}", track).into());
+    }
+    args.push("-vn".into());
+    args
+}
+
+/// Stream selection with `cover` as a second input, embedded as ID3 APIC album art
+fn cover_map_args(job: &JobOptions) -> Vec<OsString> {
+    vec![
+        "-map".into(),
+        format!("0:a:{}", job.audio_track.unwrap_or(0)).into(),
+        "-map".into(),
+        "1:v".into(),
+        "-c:v".into(),
+        "copy".into(),
+        "-disposition:v".into(),
+        "attached_pic".into(),
+        "-metadata:s:v".into(),
+        "title=Album cover".into(),
+        "-metadata:s:v".into(),
+        "comment=Cover (front)".into(),
+    ]
+}
+
+/// FFMETADATA document with a chapter every `chapter_length` seconds over `duration`
+pub fn chapters_metadata(duration: f64, chapter_length: f64) -> String {
+    let mut metadata = ";FFMETADATA1\n".to_string();
+    let mut start = 0.0;
+    let mut number = 1;
+    while start < duration {
+        let end = (start + chapter_length).min(duration);
+        metadata.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle=Chapter {}\n",
+            (start * 1000.0) as u64,
+            (end * 1000.0) as u64,
+            number
+        ));
+        start = end;
+        number += 1;
+    }
+    metadata
+}
+
+/// Length of the converted range in seconds, if the source duration is known
+fn clip_duration(job: &JobOptions, media: Option<&MediaInfo>) -> Option<f64> {
+    let end = job.trim_end.or_else(|| media.and_then(|m| m.duration))?;
+    let offset = job.audio_offset_ms as f64 / 1000.0;
+    Some((end - job.trim_start.unwrap_or(0.0) + offset).max(0.0))
+}
+
+/// Length of the output in seconds, after trimming and speed changes
+pub fn output_duration(settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> Option<f64> {
+    Some(clip_duration(job, media)? / settings.speed as f64)
+}
+
+/// Sample rate of the source audio stream being converted
+fn source_sample_rate(job: &JobOptions, media: Option<&MediaInfo>) -> Option<u32> {
+    media.and_then(|m| m.audio_stream(job.audio_track)).and_then(|s| s.sample_rate())
+}
+
+/// Chain `atempo` filters, each limited to the 0.5-2.0 range older FFmpeg builds accept
+fn atempo_chain(speed: f32) -> Vec<String> {
+    let mut filters = Vec::new();
+    let mut remaining = speed as f64;
+    while remaining > 2.0 {
+        filters.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        filters.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    filters.push(format!("atempo={}", remaining));
+    filters
+}
+
+/// Audio filter chain for the encode, `loudnorm` last so it sees the final signal
+pub fn audio_filters(settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> Vec<String> {
+    let mut filters = Vec::new();
+
+    // Delay with leading silence, or drop the start to pull the audio earlier
+    if job.audio_offset_ms > 0 {
+        filters.push(format!("adelay={}:all=1", job.audio_offset_ms));
+    } else if job.audio_offset_ms < 0 {
+        filters.push(format!(
+            "atrim=start={},asetpts=PTS-STARTPTS",
+            -job.audio_offset_ms as f64 / 1000.0
+        ));
+    }
+
+    // Fold surround sources to stereo first so the EQ sees the final mix
+    if media.is_some_and(|m| m.is_surround(job.audio_track)) {
+        match settings.downmix {
+            Downmix::Default => {}
+            Downmix::ProLogic2 => filters.push("aresample=matrix_encoding=dplii:ochl=stereo".to_string()),
+            // Center at +3 dB and surrounds at -6 dB instead of -3 dB each
+            Downmix::DialogueBoost => filters.push("aresample=ochl=stereo:clev=1.414:slev=0.5".to_string()),
+        }
+    }
+
+    filters.extend(job.eq.filters());
+
+    if settings.fade_in > 0.0 {
+        filters.push(format!("afade=t=in:st=0:d={}", settings.fade_in));
+    }
+    if settings.fade_out > 0.0 {
+        // Input seeking resets timestamps, so the clip always starts at 0
+        match clip_duration(job, media) {
+            Some(length) => {
+                let start = (length - settings.fade_out as f64).max(0.0);
+                filters.push(format!("afade=t=out:st={}:d={}", start, settings.fade_out));
+            }
+            None => println!("Skipping fade-out: source duration unknown"),
+        }
+    }
+
+    // Fades above use source timestamps, so the speed change comes after them
+    if (settings.speed - 1.0).abs() > f32::EPSILON {
+        if settings.preserve_pitch {
+            filters.extend(atempo_chain(settings.speed));
+        } else {
+            // Resampling shifts pitch along with speed, like a faster tape
+            let rate = source_sample_rate(job, media).unwrap_or(44100);
+            filters.push(format!(
+                "asetrate={},aresample={}",
+                (rate as f64 * settings.speed as f64).round(),
+                rate
+            ));
+        }
+    }
+
+    if settings.normalize {
+        let mut loudnorm = format!(
+            "loudnorm=I={}:TP={}:LRA={}",
+            settings.loudness_target, LOUDNORM_TRUE_PEAK, LOUDNORM_RANGE
+        );
+        if let Some(ref measured) = job.loudness {
+            loudnorm.push_str(&format!(
+                ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+                measured.input_i, measured.input_tp, measured.input_lra, measured.input_thresh, measured.target_offset
+            ));
+        }
+        filters.push(loudnorm);
+    }
+
+    filters
+}
+
+/// Build the FFmpeg arguments for the loudness measurement pass
+pub fn loudness_args(input: &Path, settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> Vec<OsString> {
+    let mut args = input_args(input, settings, job);
+    args.extend(audio_map_args(job));
+    let mut filters = audio_filters(settings, job, media);
+    if let Some(loudnorm) = filters.last_mut() {
+        loudnorm.push_str(":print_format=json");
+    }
+    args.push("-af".into());
+    args.push(filters.join(",").into());
+    args.push("-f".into());
+    args.push("null".into());
+    args.push("-".into());
+    args
+}
+
+/// Build the FFmpeg arguments for the silence detection pass
+pub fn silence_args(input: &Path, settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> Vec<OsString> {
+    let mut args = input_args(input, settings, job);
+    args.extend(audio_map_args(job));
+    let mut filters = audio_filters(settings, job, media);
+    filters.push(format!(
+        "silencedetect=noise={}dB:d={}",
+        SILENCE_THRESHOLD_DB, settings.silence_min_duration
+    ));
+    args.push("-af".into());
+    args.push(filters.join(",").into());
+    args.push("-f".into());
+    args.push("null".into());
+    args.push("-".into());
+    args
+}
+
+/// Build the FFmpeg arguments that write the `index`th subtitle stream as SRT
+pub fn subtitle_args(input: &Path, output: &Path, settings: &Settings, job: &JobOptions, index: usize) -> Vec<OsString> {
+    let mut args = input_args(input, settings, job);
+    args.push("-map".into());
+    args.push(format!("0:s:{}", index).into());
+    args.push("-c:s".into());
+    args.push("srt".into());
+    args.push("-y".into());
+    args.push(path_arg(output));
+    args
+}
+
+/// Build the FFmpeg arguments that render a spectrogram of `file` into the PNG `image`
+pub fn spectrogram_args(file: &Path, image: &Path) -> Vec<OsString> {
+    vec![
+        "-i".into(),
+        path_arg(file),
+        "-lavfi".into(),
+        "showspectrumpic=s=1024x512".into(),
+        "-y".into(),
+        path_arg(image),
+    ]
+}
+
+/// Build the FFmpeg arguments that analyze `file` with the `replaygain` filter
+pub fn replaygain_args(file: &Path) -> Vec<OsString> {
+    vec![
+        "-i".into(),
+        path_arg(file),
+        "-vn".into(),
+        "-af".into(),
+        "replaygain".into(),
+        "-f".into(),
+        "null".into(),
+        "-".into(),
+    ]
+}
+
+/// Build the FFmpeg arguments that copy `file` to `output` with ReplayGain TXXX tags added
+pub fn replaygain_tag_args(file: &Path, output: &Path, replaygain: &ReplayGain) -> Vec<OsString> {
+    vec![
+        "-i".into(),
+        path_arg(file),
+        "-map".into(),
+        "0".into(),
+        "-c".into(),
+        "copy".into(),
+        "-map_metadata".into(),
+        "0".into(),
+        "-metadata".into(),
+        format!("REPLAYGAIN_TRACK_GAIN={:.2} dB", replaygain.gain).into(),
+        "-metadata".into(),
+        format!("REPLAYGAIN_TRACK_PEAK={:.6}", replaygain.peak).into(),
+        "-id3v2_version".into(),
+        "3".into(),
+        "-y".into(),
+        path_arg(output),
+    ]
+}
+
+/// Output options: a single file, or numbered parts when splitting
+fn output_args(output: &Path, settings: &Settings, job: &JobOptions) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+    // Extra arguments go right before the output path, after ours, so they can override them
+    let extra_args = job.extra_args.iter().map(OsString::from);
+
+    let split = match settings.split {
+        SplitMode::Silence if !job.split_points.is_empty() => {
+            let times: Vec<String> = job.split_points.iter().map(|t| format!("{:.3}", t)).collect();
+            Some(("-segment_times", times.join(",")))
+        }
+        SplitMode::Fixed => Some(("-segment_time", (settings.segment_minutes * 60).to_string())),
+        _ => None,
+    };
+
+    if let Some((split_arg, split_value)) = split {
+        args.push("-f".into());
+        args.push("segment".into());
+        args.push("-segment_format".into());
+        args.push(settings.format.muxer().to_string().into());
+        args.push(split_arg.to_string().into());
+        args.push(split_value.into());
+        if let Some(ref list) = job.segment_list {
+            args.push("-segment_list".into());
+            args.push(path_arg(list));
+            args.push("-segment_list_type".into());
+            args.push("flat".into());
+        }
+        args.push("-segment_start_number".into());
+        args.push("1".into());
+        args.push("-reset_timestamps".into());
+        args.push("1".into());
+        args.push("-y".into());
+        args.extend(extra_args);
+        args.push(segment_pattern(output));
+        return args;
+    }
+
+    args.push("-f".into());
+    args.push(settings.format.muxer().to_string().into());
+    args.push("-y".into());
+    args.extend(extra_args);
+    args.push(path_arg(output));
+    args
+}
+
+/// Build the FFmpeg arguments for converting `input` to `output` in the configured format
+///
+/// `media` is the probe result for `input`, if available.
+pub fn conversion_args(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    job: &JobOptions,
+    media: Option<&MediaInfo>,
+) -> Vec<OsString> {
+    let mut args = input_args(input, settings, job);
+
+    // Extra inputs: cover art is input 1, generated chapters come after it
+    let mut next_input = 1;
+    if let Some(ref cover) = job.cover {
+        args.push("-i".into());
+        args.push(path_arg(cover));
+        next_input += 1;
+    }
+    if let Some(ref chapters) = job.chapters_file {
+        args.push("-i".into());
+        args.push(path_arg(chapters));
+    }
+
+    match job.cover {
+        Some(_) => args.extend(cover_map_args(job)),
+        None => args.extend(audio_map_args(job)),
+    }
+    args.push("-map_chapters".into());
+    args.push((if job.chapters_file.is_some() { next_input } else { 0 }.to_string()).into());
+    if settings.format == OutputFormat::Mp3 {
+        // ID3v2.3 is what Windows Explorer and most players read
+        args.push("-id3v2_version".into());
+        args.push("3".into());
+    }
+    // User-entered tags come after, so they take precedence over copied ones
+    args.push("-map_metadata".into());
+    args.push((if settings.copy_metadata { "0" } else { "-1" }.to_string()).into());
+    args.extend(job.tags.metadata_args().into_iter().map(OsString::from));
+    let filters = audio_filters(settings, job, media);
+
+    if copies_stream(settings, job, media) {
+        args.push("-c:a".into());
+        args.push("copy".into());
+        args.extend(output_args(output, settings, job));
+        return args;
+    }
+
+    match settings.format {
+        OutputFormat::Mp3 => {
+            args.push("-acodec".into());
+            args.push(settings.format.encoder().to_string().into());
+
+            match settings.mode {
+                EncodeMode::Cbr => {
+                    args.push("-ab".into());
+                    args.push(format!("{}k", settings.bitrate).into());
+                }
+                EncodeMode::Vbr => {
+                    // LAME VBR scale: 0 = best quality, 9 = smallest file
+                    args.push("-q:a".into());
+                    args.push(settings.vbr_quality.to_string().into());
+                }
+            }
+        }
+        OutputFormat::Flac => {
+            args.push("-c:a".into());
+            args.push(settings.format.encoder().to_string().into());
+        }
+        OutputFormat::M4b | OutputFormat::M4r => {
+            // FFmpeg's AAC encoder has no usable VBR mode, always use the bitrate
+            args.push("-c:a".into());
+            args.push(settings.format.encoder().to_string().into());
+            args.push("-b:a".into());
+            args.push(format!("{}k", settings.bitrate).into());
+        }
+    }
+
+    if !filters.is_empty() {
+        args.push("-af".into());
+        args.push(filters.join(",").into());
+    }
+    args.extend(resample_args(settings, job, media));
+    args.extend(output_args(output, settings, job));
+    args
+}
+
+/// Whether the source audio already has the output's codec and nothing needs filtering or
+/// resampling, so it is copied without re-encoding
+pub fn copies_stream(settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> bool {
+    settings.stream_copy
+        && settings.sample_rate.is_none()
+        && settings.channels == Channels::Keep
+        && audio_filters(settings, job, media).is_empty()
+        && media.is_some_and(|m| m.can_copy_to(settings.format, job.audio_track))
+}
+
+/// Build the FFmpeg arguments for decoding, trimming and filtering `input` once into a lossless `output`
+///
+/// Used when there are several output formats, which are then all encoded from `output`.
+/// Global tags and chapters are carried over for them.
+pub fn decode_args(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    job: &JobOptions,
+    media: Option<&MediaInfo>,
+) -> Vec<OsString> {
+    let mut args = input_args(input, settings, job);
+    args.extend(audio_map_args(job));
+    args.push("-map_metadata".into());
+    args.push("0".into());
+    args.push("-map_chapters".into());
+    args.push("0".into());
+    let filters = audio_filters(settings, job, media);
+    if !filters.is_empty() {
+        args.push("-af".into());
+        args.push(filters.join(",").into());
+    }
+    args.extend(resample_args(settings, job, media));
+    args.push("-c:a".into());
+    args.push("flac".into());
+    args.push("-f".into());
+    args.push("matroska".into());
+    args.push("-y".into());
+    args.push(path_arg(output));
+    args
+}
+
+/// Sample rate and channel count options for the encode
+fn resample_args(settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+
+    // loudnorm resamples to 192 kHz internally, so pin the rate back to the source's
+    let sample_rate = match settings.sample_rate {
+        Some(rate) => Some(rate),
+        None if settings.normalize => Some(source_sample_rate(job, media).unwrap_or(44100)),
+        None => None,
+    };
+    if let Some(rate) = sample_rate {
+        args.push("-ar".into());
+        args.push(rate.to_string().into());
+    }
+
+    match settings.channels {
+        Channels::Keep => {}
+        Channels::Stereo => {
+            args.push("-ac".into());
+            args.push("2".into());
+        }
+        Channels::Mono => {
+            args.push("-ac".into());
+            args.push("1".into());
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(input: &Path, output: &Path) -> Vec<OsString> {
+        conversion_args(input, output, &Settings::default(), &JobOptions::default(), None)
+    }
+
+    #[test]
+    fn unicode_paths_are_passed_unchanged() {
+        let input = Path::new("/videos/Café – 日本語 🎵.mkv");
+        let output = Path::new("/music/Café – 日本語 🎵.mp3");
+        let args = convert(input, output);
+        assert!(args.contains(&input.as_os_str().to_os_string()));
+        assert!(args.contains(&output.as_os_str().to_os_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_are_passed_unchanged() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let input = Path::new(OsStr::from_bytes(b"/videos/caf\xe9.mkv"));
+        let output = Path::new(OsStr::from_bytes(b"/music/caf\xe9.mp3"));
+        let args = convert(input, output);
+        assert!(args.contains(&input.as_os_str().to_os_string()));
+        assert!(args.contains(&output.as_os_str().to_os_string()));
+        assert!(command_line(&args).contains("caf\u{fffd}.mp3"));
+    }
+
+    #[test]
+    fn long_paths_are_passed_whole() {
+        let dir = "ü".repeat(150);
+        let output = PathBuf::from(format!("/music/{}/{}/track 🎵.mp3", dir, dir));
+        let args = convert(Path::new("/videos/input.mkv"), &output);
+        assert_eq!(args.last(), Some(&path_arg(&output)));
+    }
+
+    #[test]
+    fn long_windows_paths_get_extended_prefix() {
+        let name = "a".repeat(MAX_PATH);
+        assert_eq!(
+            extended_length(Path::new(&format!(r"C:\music\{}.mp3", name))),
+            PathBuf::from(format!(r"\\?\C:\music\{}.mp3", name))
+        );
+        assert_eq!(
+            extended_length(Path::new(&format!("C:/music/{}.mp3", name))),
+            PathBuf::from(format!(r"\\?\C:\music\{}.mp3", name))
+        );
+        assert_eq!(
+            extended_length(Path::new(&format!(r"\\server\share\{}.mp3", name))),
+            PathBuf::from(format!(r"\\?\UNC\server\share\{}.mp3", name))
+        );
+    }
+
+    #[test]
+    fn short_and_relative_paths_are_left_alone() {
+        let short = Path::new(r"C:\music\track.mp3");
+        assert_eq!(extended_length(short), short);
+        let relative = PathBuf::from(format!(r"music\{}.mp3", "a".repeat(MAX_PATH)));
+        assert_eq!(extended_length(&relative), relative);
+        let prefixed = PathBuf::from(format!(r"\\?\C:\{}.mp3", "a".repeat(MAX_PATH)));
+        assert_eq!(extended_length(&prefixed), prefixed);
+    }
+
+    #[test]
+    fn segment_pattern_escapes_percent() {
+        assert_eq!(
+            segment_pattern(Path::new("/music/100% 🎵/mix 50%.mp3")),
+            OsString::from("/music/100%% 🎵/mix 50%% %02d.mp3")
+        );
+    }
+
+    #[test]
+    fn stream_copy_gives_way_to_resampling() {
+        use crate::probe::StreamInfo;
+
+        let mut stream = StreamInfo::default();
+        stream.codec_type = "audio".to_string();
+        stream.codec_name = "mp3".to_string();
+        let media = MediaInfo {
+            streams: vec![stream],
+            ..MediaInfo::default()
+        };
+        let copies = |settings: Settings| {
+            let args = conversion_args(Path::new("in.mkv"), Path::new("out.mp3"), &settings, &JobOptions::default(), Some(&media));
+            args.windows(2).any(|pair| pair[0] == "-c:a" && pair[1] == "copy")
+        };
+        let copy = Settings {
+            stream_copy: true,
+            ..Settings::default()
+        };
+        assert!(copies(copy.clone()));
+        assert!(!copies(Settings {
+            sample_rate: Some(44100),
+            ..copy.clone()
+        }));
+        assert!(!copies(Settings {
+            channels: Channels::Mono,
+            ..copy
+        }));
+    }
+
+    #[test]
+    fn extra_args_come_after_ours_before_the_output() {
+        let job = JobOptions {
+            extra_args: vec!["-f".to_string(), "adts".to_string()],
+            ..JobOptions::default()
+        };
+        let output = Path::new("out.mp3");
+        let args = conversion_args(Path::new("in.mkv"), output, &Settings::default(), &job, None);
+        let user = args.iter().rposition(|arg| arg == "adts").unwrap();
+        let ours = args.iter().position(|arg| arg == "-f").unwrap();
+        assert!(ours < user - 1);
+        assert_eq!(args[user + 1], output.as_os_str());
+    }
+}
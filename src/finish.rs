@@ -0,0 +1,137 @@
+use std::path::Path;
+use std::process::Command;
+
+/// What to do once the last queue item is converted
+#[derive(Clone, Copy, PartialEq)]
+pub enum FinishAction {
+    Nothing,
+    OpenFolder,
+    PlaySound,
+    Sleep,
+    Shutdown,
+}
+
+impl FinishAction {
+    pub const ALL: [FinishAction; 5] = [
+        FinishAction::Nothing,
+        FinishAction::OpenFolder,
+        FinishAction::PlaySound,
+        FinishAction::Sleep,
+        FinishAction::Shutdown,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FinishAction::Nothing => "Do nothing",
+            FinishAction::OpenFolder => "Open output folder",
+            FinishAction::PlaySound => "Play a sound",
+            FinishAction::Sleep => "Sleep",
+            FinishAction::Shutdown => "Shut down",
+        }
+    }
+
+    /// Carry out the action, `output` is one of the files the batch wrote
+    pub fn run(self, output: Option<&Path>) {
+        println!("Batch finished: {}", self.name());
+        match self {
+            FinishAction::Nothing => {}
+            FinishAction::OpenFolder => {
+                if let Some(output) = output {
+                    reveal(output);
+                }
+            }
+            FinishAction::PlaySound => play_sound(),
+            FinishAction::Sleep => sleep(),
+            FinishAction::Shutdown => shutdown(),
+        }
+    }
+}
+
+/// Show `path` in the system file manager
+pub fn reveal(path: &Path) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = hidden_command("explorer").arg("/select,").arg(path).spawn();
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = Command::new("open").arg("-R").arg(path).spawn();
+    }
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(parent) = path.parent() {
+            let _ = Command::new("xdg-open").arg(parent).spawn();
+        }
+    }
+}
+
+/// Open `path` in the program the system uses for its type, e.g. the default audio player
+pub fn open(path: &Path) {
+    #[cfg(target_os = "windows")]
+    {
+        // Explorer opens files like `start` does, without cmd reading `&` or `^` in the name
+        let _ = hidden_command("explorer").arg(path).spawn();
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = Command::new("open").arg(path).spawn();
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let _ = Command::new("xdg-open").arg(path).spawn();
+    }
+}
+
+fn play_sound() {
+    #[cfg(target_os = "windows")]
+    let result = hidden_command("powershell")
+        .args(["-NoProfile", "-Command", "[System.Media.SystemSounds]::Asterisk.Play(); Start-Sleep 1"])
+        .spawn();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("afplay").arg("/System/Library/Sounds/Glass.aiff").spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("canberra-gtk-play").args(["-i", "complete"]).spawn();
+
+    if let Err(e) = result {
+        println!("Failed to play sound: {}", e);
+    }
+}
+
+fn sleep() {
+    #[cfg(target_os = "windows")]
+    let result = hidden_command("rundll32.exe").args(["powrprof.dll,SetSuspendState", "0,1,0"]).spawn();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("pmset").arg("sleepnow").spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("systemctl").arg("suspend").spawn();
+
+    if let Err(e) = result {
+        println!("Failed to put the computer to sleep: {}", e);
+    }
+}
+
+fn shutdown() {
+    // Windows waits a minute, so `shutdown /a` can still call it off
+    #[cfg(target_os = "windows")]
+    let result = hidden_command("shutdown").args(["/s", "/t", "60"]).spawn();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("osascript")
+        .args(["-e", "tell application \"System Events\" to shut down"])
+        .spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("systemctl").arg("poweroff").spawn();
+
+    if let Err(e) = result {
+        println!("Failed to shut down: {}", e);
+    }
+}
+
+/// Command that doesn't flash a console window
+#[cfg(target_os = "windows")]
+fn hidden_command(program: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let mut cmd = Command::new(program);
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Translations shipped inside the app, as gettext `.po` catalogs
+const BUILT_IN: [(&str, &str); 1] = [("de", include_str!("../locales/de.po"))];
+
+/// English text of the interface mapped to the chosen language, empty for English
+///
+/// Like gettext, the English text in the code is the message id, so anything a catalog
+/// doesn't translate shows up in English.
+static CATALOG: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// `msgid` in the current language
+pub fn tr(msgid: &str) -> String {
+    CATALOG.lock().unwrap().get(msgid).cloned().unwrap_or_else(|| msgid.to_string())
+}
+
+/// `msgid` in the current language with `{name}` placeholders filled in from `args`
+pub fn tr_args(msgid: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter()
+        .fold(tr(msgid), |text, (name, value)| text.replace(&format!("{{{}}}", name), &value.to_string()))
+}
+
+/// A language that can be picked, English or one with a catalog
+pub struct Language {
+    /// Language code, e.g. `de`, the catalog's file name
+    pub code: String,
+    /// Name of the language in itself, e.g. `Deutsch`
+    pub name: String,
+}
+
+/// English, the built-in translations, then catalogs found in the config folder's `locales`
+pub fn languages() -> Vec<Language> {
+    let mut languages = vec![Language {
+        code: "en".to_string(),
+        name: "English".to_string(),
+    }];
+    let mut add = |code: &str, contents: &str| {
+        if !languages.iter().any(|l| l.code == code) {
+            let name = header(contents, "X-Language-Name").unwrap_or_else(|| code.to_string());
+            languages.push(Language {
+                code: code.to_string(),
+                name,
+            });
+        }
+    };
+    for (code, contents) in BUILT_IN {
+        add(code, contents);
+    }
+    for (code, path) in user_catalogs() {
+        if let Ok(contents) = fs::read_to_string(&path) {
+            add(&code, &contents);
+        }
+    }
+    languages
+}
+
+/// Switch the interface to `code`, English when there's no catalog for it
+///
+/// A catalog in the config folder wins over a built-in one, so translations can be fixed without a new build.
+pub fn set_language(code: &str) {
+    let contents = user_catalogs()
+        .into_iter()
+        .find(|(c, _)| c == code)
+        .and_then(|(_, path)| fs::read_to_string(&path).ok())
+        .or_else(|| BUILT_IN.iter().find(|(c, _)| *c == code).map(|(_, contents)| contents.to_string()));
+    *CATALOG.lock().unwrap() = match contents {
+        Some(contents) => parse(&contents),
+        None => BTreeMap::new(),
+    };
+}
+
+/// `.po` files in the config folder's `locales`, by language code
+fn user_catalogs() -> Vec<(String, PathBuf)> {
+    let Some(dir) = directories::ProjectDirs::from("", "", "vid2mp3").map(|dirs| dirs.config_dir().join("locales")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "po" {
+                return None;
+            }
+            Some((path.file_stem()?.to_str()?.to_string(), path))
+        })
+        .collect()
+}
+
+/// Value of a `Name: value` line in the catalog's header entry
+fn header(contents: &str, name: &str) -> Option<String> {
+    let header = parse_entries(contents).into_iter().find(|(id, _)| id.is_empty())?.1;
+    header.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == name).then(|| value.trim().to_string())
+    })
+}
+
+/// Translated entries of a catalog, untranslated ones are left out so they fall back to English
+fn parse(contents: &str) -> BTreeMap<String, String> {
+    parse_entries(contents)
+        .into_iter()
+        .filter(|(id, text)| !id.is_empty() && !text.is_empty())
+        .collect()
+}
+
+/// `msgid`/`msgstr` pairs of a `.po` file, each string possibly continued over several quoted lines
+fn parse_entries(contents: &str) -> Vec<(String, String)> {
+    enum Field {
+        None,
+        Id,
+        Text,
+    }
+    let mut entries = Vec::new();
+    let mut id = String::new();
+    let mut text = String::new();
+    let mut field = Field::None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            if let Field::Text = field {
+                entries.push((std::mem::take(&mut id), std::mem::take(&mut text)));
+            }
+            id = unquote(rest);
+            field = Field::Id;
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            text = unquote(rest);
+            field = Field::Text;
+        } else if line.starts_with('"') {
+            match field {
+                Field::Id => id += &unquote(line),
+                Field::Text => text += &unquote(line),
+                Field::None => {}
+            }
+        }
+    }
+    if let Field::Text = field {
+        entries.push((id, text));
+    }
+    entries
+}
+
+/// Contents of a `"..."` string with its C escapes resolved
+fn unquote(quoted: &str) -> String {
+    let quoted = quoted.trim();
+    let inner = quoted.strip_prefix('"').and_then(|q| q.strip_suffix('"')).unwrap_or_default();
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG_FILE: &str = r#"
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+"X-Language-Name: Deutsch\n"
+
+# A comment
+msgid "Convert"
+msgstr "Konvertieren"
+
+msgid ""
+"Converted {count} files, "
+"{failed} failed"
+msgstr ""
+"{count} Dateien konvertiert, "
+"{failed} fehlgeschlagen"
+
+msgid "Not translated yet"
+msgstr ""
+"#;
+
+    #[test]
+    fn entries_continue_over_several_lines() {
+        let entries = parse_entries(CATALOG_FILE);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1], ("Convert".to_string(), "Konvertieren".to_string()));
+        assert_eq!(
+            entries[2],
+            (
+                "Converted {count} files, {failed} failed".to_string(),
+                "{count} Dateien konvertiert, {failed} fehlgeschlagen".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unquote_resolves_escapes() {
+        assert_eq!(unquote(r#""Line one\nLine two""#), "Line one\nLine two");
+        assert_eq!(unquote(r#""Say \"hi\"\t\\ done""#), "Say \"hi\"\t\\ done");
+        assert_eq!(unquote("  \"padded\"  "), "padded");
+        assert_eq!(unquote("not quoted"), "");
+    }
+
+    #[test]
+    fn untranslated_entries_fall_back_to_english() {
+        let catalog = parse(CATALOG_FILE);
+        assert_eq!(catalog.get("Convert").map(String::as_str), Some("Konvertieren"));
+        assert!(!catalog.contains_key("Not translated yet"));
+        assert!(!catalog.contains_key(""));
+
+        *CATALOG.lock().unwrap() = catalog;
+        assert_eq!(tr("Convert"), "Konvertieren");
+        assert_eq!(tr("Not translated yet"), "Not translated yet");
+        assert_eq!(
+            tr_args("Converted {count} files, {failed} failed", &[("count", &3), ("failed", &1)]),
+            "3 Dateien konvertiert, 1 fehlgeschlagen"
+        );
+        CATALOG.lock().unwrap().clear();
+    }
+
+    #[test]
+    fn header_reads_language_name() {
+        assert_eq!(header(CATALOG_FILE, "X-Language-Name").as_deref(), Some("Deutsch"));
+        assert_eq!(header(CATALOG_FILE, "Language"), None);
+        assert_eq!(header("msgid \"Convert\"\nmsgstr \"\"", "X-Language-Name"), None);
+    }
+}
@@ -0,0 +1,30 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Lines kept before the oldest ones are dropped
+const MAX_LINES: usize = 5000;
+
+/// FFmpeg output of every conversion since the app started
+static LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Add a line to the log
+pub fn append(line: impl Into<String>) {
+    let mut log = LOG.lock().unwrap();
+    if log.len() == MAX_LINES {
+        log.pop_front();
+    }
+    log.push_back(line.into());
+}
+
+/// The whole log as text
+pub fn text() -> String {
+    LOG.lock().unwrap().iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+}
+
+pub fn len() -> usize {
+    LOG.lock().unwrap().len()
+}
+
+pub fn clear() {
+    LOG.lock().unwrap().clear();
+}
@@ -1,35 +1,209 @@
 #![windows_subsystem = "windows"]
 
+mod assets;
+mod converter;
+mod cue;
+mod diagnose;
+mod download;
+mod ffmpeg;
+mod finish;
+mod i18n;
+mod log;
+#[cfg(feature = "native")]
+mod native;
+mod notify;
+mod pipeline;
+mod playlist;
+mod preset;
+mod probe;
+mod profile;
+mod progress;
+mod queue;
+mod settings;
+mod tags;
+mod taskbar;
+mod tray;
+mod update;
+mod timecode;
+
 use eframe::egui::{self, Color32, ColorImage, CornerRadius, IconData, Stroke, TextureHandle, Vec2};
+use ffmpeg::{EqPreset, JobOptions};
+use download::DownloadState;
+use finish::FinishAction;
+use i18n::{tr, tr_args};
+use pipeline::Conversion;
+use preset::Preset;
+use profile::Profile;
+use probe::MediaInfo;
+use progress::Progress;
+use queue::{ItemState, Queue, QueueItem};
 use rfd::FileDialog;
-use std::path::PathBuf;
+use settings::{BITRATES, Channels, Downmix, EncodeMode, ExistingOutput, OutputFormat, RINGTONE_LENGTH, SAMPLE_RATES, Settings, SourceAction, SplitMode, Theme};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tags::Tags;
+use taskbar::{Taskbar, TaskbarState};
+use tray::{Tray, TrayCommand, TrayState};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::process::Command;
 use tokio::runtime::Runtime;
 
+/// Video containers offered in the file dialog
+const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mkv", "avi", "mov", "webm", "flv"];
+
+/// Audio files that are transcoded directly
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "m4a", "aac", "flac", "wav", "ogg", "opus"];
+
+/// Whether `path` has one of the extensions the app converts
+fn is_supported_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.as_str()) || AUDIO_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Whether `path` looks like an audio-only file
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Files and folders on the clipboard, copied in a file manager or as `text` holding one path per line
+fn clipboard_paths(text: Option<&str>) -> Vec<PathBuf> {
+    let files = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get().file_list());
+    if let Ok(files) = files
+        && !files.is_empty()
+    {
+        return files;
+    }
+
+    let text = match text {
+        Some(text) => text.to_string(),
+        None => arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).unwrap_or_default(),
+    };
+    text.lines()
+        .map(|line| line.trim().trim_matches('"'))
+        .map(|line| PathBuf::from(line.strip_prefix("file://").unwrap_or(line)))
+        .filter(|path| path.is_dir() || is_supported_file(path) || playlist::is_playlist(path))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Width of the main column, the original 250 points growing with the window up to a readable line length
+fn content_width(ui: &egui::Ui) -> f32 {
+    (ui.available_width() - 50.0).clamp(250.0, 640.0)
+}
+
+/// Colour of labels that sit back from the main text, readable in both themes
+fn secondary_text(ui: &egui::Ui) -> Color32 {
+    if ui.visuals().dark_mode { Color32::LIGHT_GRAY } else { Color32::DARK_GRAY }
+}
+
+/// Hover text that also names an icon-only button for screen readers, which would otherwise read out the symbol
+fn icon_hint(response: egui::Response, text: &str) -> egui::Response {
+    let enabled = response.enabled();
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, enabled, text));
+    response.on_hover_text(text)
+}
+
+/// Have screen readers read out `text` whenever it changes, without moving the focus
+fn announce(ctx: &egui::Context, id: &str, text: &str) {
+    use egui::accesskit::{Live, Role};
+    ctx.accesskit_node_builder(egui::Id::new(id), |node| {
+        node.set_role(Role::Label);
+        node.set_value(text);
+        node.set_live(Live::Polite);
+    });
+}
+
+/// State of a queue item as read out by screen readers
+fn item_state_name(item: &QueueItem) -> String {
+    match item.state {
+        ItemState::Waiting if item.high_priority => tr("waiting, high priority"),
+        ItemState::Waiting => tr("waiting"),
+        ItemState::Converting => tr("converting"),
+        ItemState::Done(_) => tr("done"),
+        ItemState::Suspicious(..) => tr("done, but suspicious"),
+        ItemState::Failed(_) => tr("failed"),
+        ItemState::Skipped => tr("skipped"),
+    }
+}
+
+/// Human-readable file size, e.g. `4.3 MB`
+fn format_size(bytes: u64) -> String {
+    let mb = bytes as f64 / 1_000_000.0;
+    if mb >= 1.0 {
+        format!("{:.1} MB", mb)
+    } else {
+        format!("{} KB", bytes / 1000)
+    }
+}
+
+/// Save `preset` to a file picked by the user
+fn export_preset(preset: &Preset) {
+    let Some(path) = FileDialog::new()
+        .add_filter(tr("Preset"), &["json"])
+        .set_file_name(format!("{}.json", preset.name))
+        .save_file()
+    else {
+        return;
+    };
+    if let Err(e) = preset.save(&path) {
+        println!("Failed to export preset '{}': {}", path.display(), e);
+    }
+}
+
 fn load_icon() -> Option<Arc<IconData>> {
-    let icon_path = "assets/icon.ico";
-    match image::open(icon_path) {
+    match image::load_from_memory(assets::ICON) {
         Ok(img) => {
             let img = img.to_rgba8();
             let (width, height) = img.dimensions();
             let pixels = img.into_raw();
             Some(Arc::new(IconData {
                 rgba: pixels,
-                width: width,
-                height: height,
+                width,
+                height,
             }))
         }
-        Err(_) => None,
+        Err(e) => {
+            println!("Failed to decode the app icon: {}", e);
+            None
+        }
     }
 }
 
+/// Upload `img` to the GPU so it can be drawn
+fn texture(ctx: &egui::Context, name: &str, img: image::DynamicImage) -> TextureHandle {
+    let size = [img.width() as usize, img.height() as usize];
+    let img_buffer = img.to_rgba8();
+    let pixels = img_buffer.as_flat_samples();
+    let color_image = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+    ctx.load_texture(name, color_image, Default::default())
+}
+
 fn main() -> eframe::Result<()> {
     let rt = Runtime::new().unwrap();
 
+    // Files passed on the command line, e.g. from "Open with"; `--auto` converts them right away
+    let mut auto = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args_os().skip(1) {
+        if arg == "--auto" {
+            auto = true;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+
+    // Reopen where the window was left, the platform places it when that isn't known
+    let settings = Settings::load();
     let mut viewport = egui::ViewportBuilder::default()
-        .with_inner_size([300.0, 320.0])
-        .with_resizable(false);
+        .with_inner_size(settings.window_size.unwrap_or([300.0, 390.0]))
+        .with_min_inner_size([300.0, 390.0]);
+    if let Some(position) = settings.window_position {
+        viewport = viewport.with_position(position);
+    }
 
     if let Some(icon) = load_icon() {
         viewport = viewport.with_icon(icon);
@@ -43,7 +217,21 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Video to MP3",
         options,
-        Box::new(|_cc| Ok(Box::new(App::new(rt)))),
+        Box::new(|cc| {
+            cc.egui_ctx.set_theme(settings.theme.preference());
+            let mut app = App::new(rt, settings);
+            app.info_icon = image::load_from_memory(assets::INFO_ICON)
+                .ok()
+                .map(|img| texture(&cc.egui_ctx, "info_icon", img));
+            app.taskbar = Some(Taskbar::new(cc));
+            app.check_ffmpeg();
+            app.tray = Tray::new(&cc.egui_ctx, load_icon().as_deref());
+            app.open_paths(paths, auto);
+            if app.settings.check_updates {
+                update::check(Arc::clone(&app.update));
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
 
@@ -51,79 +239,318 @@ struct App {
     runtime: Runtime,
     input_path: Option<PathBuf>,
     output_path: Option<PathBuf>,
+    /// Output filename without extension, as typed
+    output_name: String,
     status: Arc<Mutex<Status>>,
+    /// Set to stop the running single-file conversion
+    cancel_conversion: Arc<AtomicBool>,
     dropped_file: bool,
     info_icon: Option<TextureHandle>,
     show_info_popup: bool,
+    /// Settings window is open
+    show_settings: bool,
+    /// Extension typed in to map to a preset
+    new_extension: String,
+    /// Reset was clicked once and waits for confirmation
+    confirm_reset: bool,
     video_thumbnail: Option<TextureHandle>,
     thumbnail_path: Arc<Mutex<Option<PathBuf>>>,
     thumbnail_loading: bool,
+    media_info: Arc<Mutex<Option<MediaInfo>>>,
+    /// Why the selected file can't be converted, found by probing it
+    input_problem: Arc<Mutex<Option<String>>>,
+    trim_start_text: String,
+    trim_end_text: String,
+    audio_track: Option<usize>,
+    /// Convert every audio track into its own file
+    all_tracks: bool,
+    audio_offset_ms: i32,
+    tags: Tags,
+    ringtone: bool,
+    ringtone_start: f64,
+    eq: EqPreset,
+    /// Name being typed for a new custom preset
+    new_preset_name: Option<String>,
+    /// Name being typed for a new profile, `Some` while the name field is shown
+    new_profile_name: Option<String>,
+    /// FFmpeg commands shown in the preview window
+    command_preview: Option<String>,
+    /// Files dropped together, converted one after another
+    queue: Queue,
+    /// Time typed for a scheduled batch start
+    schedule_text: String,
+    /// When the waiting queue items start converting on their own
+    scheduled_start: Option<chrono::DateTime<chrono::Local>>,
+    /// Run after the last queue item is converted
+    finish_action: FinishAction,
+    /// Whether the queue was converting on the previous frame
+    queue_was_running: bool,
+    /// Whether the single conversion was running on the previous frame
+    was_converting: bool,
+    /// Progress on the taskbar button, set up once the window exists
+    taskbar: Option<Taskbar>,
+    /// Tray icon, the window closes to it while work is running
+    tray: Option<Tray>,
+    /// Set when FFmpeg couldn't be started, to offer downloading it
+    ffmpeg_missing: Arc<AtomicBool>,
+    ffmpeg_download: Arc<Mutex<DownloadState>>,
+    /// Newer release found by the startup check
+    update: Arc<Mutex<Option<update::Release>>>,
+    /// Download of the newer release's installer or archive
+    update_download: Arc<Mutex<DownloadState>>,
+    /// Encoders the FFmpeg build has, `None` until they are known
+    encoders: Arc<Mutex<Option<HashSet<String>>>>,
+    /// Outputs that already exist, shown while asking whether to replace them
+    overwrite_prompt: Option<Vec<PathBuf>>,
+    /// "Remember my choice" in the overwrite prompt
+    remember_overwrite: bool,
+    /// Set once Quit is picked from the tray, so closing the window isn't turned into hiding it
+    quitting: bool,
+    /// Window was hidden to the tray while converting
+    hidden_to_tray: bool,
+    /// Last quarter of the progress announced while hidden to the tray
+    milestone: u8,
+    /// Batch left unfinished when the app last quit, offered for resuming
+    interrupted: Option<queue::SavedQueue>,
+    /// Shown under the queue after files were skipped
+    queue_warning: Option<String>,
+    /// Queue item whose per-file settings are shown
+    editing_item: Option<u64>,
+    /// Workers stop claiming queue items once this is set
+    queue_paused: Arc<AtomicBool>,
+    /// Folder batch outputs go to, `None` puts them next to their inputs
+    batch_output_dir: Option<PathBuf>,
+    settings: Settings,
+    saved_settings: Settings,
 }
 
 #[derive(Clone)]
 enum Status {
     Idle,
-    Converting,
-    Done,
+    /// Conversion running, with how far it has got
+    Converting(Progress),
+    /// Conversion finished, with the files that were written and how long it took
+    Done(Vec<PathBuf>, std::time::Duration),
+    /// Conversion finished but the output doesn't look complete
+    Suspicious(Vec<PathBuf>, String),
     Error(String),
 }
 
 impl App {
-    fn new(runtime: Runtime) -> Self {
+    fn new(runtime: Runtime, settings: Settings) -> Self {
+        ffmpeg::set_paths(&settings.ffmpeg_path, &settings.ffprobe_path);
+        i18n::set_language(&settings.language);
         Self {
             runtime,
             input_path: None,
             output_path: None,
+            output_name: String::new(),
             status: Arc::new(Mutex::new(Status::Idle)),
+            cancel_conversion: Arc::new(AtomicBool::new(false)),
             dropped_file: false,
             info_icon: None,
             show_info_popup: false,
+            show_settings: false,
+            new_extension: String::new(),
+            confirm_reset: false,
             video_thumbnail: None,
             thumbnail_path: Arc::new(Mutex::new(None)),
             thumbnail_loading: false,
+            media_info: Arc::new(Mutex::new(None)),
+            input_problem: Arc::new(Mutex::new(None)),
+            trim_start_text: String::new(),
+            trim_end_text: String::new(),
+            audio_track: None,
+            all_tracks: false,
+            audio_offset_ms: 0,
+            tags: Tags::default(),
+            ringtone: false,
+            ringtone_start: 0.0,
+            eq: EqPreset::Flat,
+            new_preset_name: None,
+            new_profile_name: None,
+            command_preview: None,
+            queue: Arc::new(Mutex::new(Vec::new())),
+            schedule_text: String::new(),
+            scheduled_start: None,
+            finish_action: FinishAction::Nothing,
+            queue_was_running: false,
+            was_converting: false,
+            taskbar: None,
+            tray: None,
+            ffmpeg_missing: Arc::new(AtomicBool::new(false)),
+            ffmpeg_download: Arc::new(Mutex::new(DownloadState::Idle)),
+            update: Arc::new(Mutex::new(None)),
+            update_download: Arc::new(Mutex::new(DownloadState::Idle)),
+            encoders: Arc::new(Mutex::new(None)),
+            overwrite_prompt: None,
+            remember_overwrite: false,
+            quitting: false,
+            hidden_to_tray: false,
+            milestone: 0,
+            interrupted: queue::load_interrupted(),
+            queue_warning: None,
+            editing_item: None,
+            queue_paused: Arc::new(AtomicBool::new(false)),
+            batch_output_dir: None,
+            saved_settings: settings.clone(),
+            settings,
         }
     }
 
-    fn load_icon_from_file(&mut self, ctx: &egui::Context, path: &str) -> Option<TextureHandle> {
+    fn load_icon_from_file(&mut self, ctx: &egui::Context, path: impl AsRef<Path>) -> Option<TextureHandle> {
+        let path = path.as_ref();
         match image::open(path) {
             Ok(img) => {
                 println!("Image opened successfully: {}x{}", img.width(), img.height());
-                let size = [img.width() as usize, img.height() as usize];
-                let img_buffer = img.to_rgba8();
-                let pixels = img_buffer.as_flat_samples();
-                let color_image = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
-
-                Some(ctx.load_texture(
-                    "thumbnail",
-                    color_image,
-                    Default::default()
-                ))
+                Some(texture(ctx, "thumbnail", img))
             }
             Err(e) => {
-                println!("Failed to open image '{}': {}", path, e);
+                println!("Failed to open image '{}': {}", path.display(), e);
                 None
             }
         }
     }
 
-    fn set_input(&mut self, path: PathBuf) {
-        let mut output = path.clone();
+    /// Where `input` is converted to: the output folder when one is set, otherwise next to it
+    fn default_output(&self, input: &Path) -> PathBuf {
+        let mut output = match self.settings.output_dir {
+            Some(ref dir) => dir.join(input.file_name().unwrap_or_default()),
+            None => input.to_path_buf(),
+        };
         output.set_extension("mp3");
-        self.output_path = Some(output);
+        output
+    }
+
+    /// Convert to `output`, showing its name in the filename field
+    fn set_output(&mut self, output: Option<PathBuf>) {
+        self.output_name = output
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.output_path = output;
+    }
+
+    /// Why the typed output name can't be used
+    fn output_name_error(&self) -> Option<String> {
+        let name = self.output_name.trim();
+        if name.is_empty() {
+            Some(tr("Enter a name for the output file"))
+        } else if name.contains(['/', '\\']) || name == "." || name == ".." {
+            Some(tr("The name can't contain slashes, change the output folder instead"))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the selected file is ready to convert
+    ///
+    /// Waits for the probe, so files without audio never reach FFmpeg.
+    fn can_convert(&self) -> bool {
+        self.input_path.is_some()
+            && self.output_name_error().is_none()
+            && self.media_info.lock().unwrap().is_some()
+            && self.input_problem.lock().unwrap().is_none()
+            && self.job_options().is_ok()
+            && !matches!(*self.status.lock().unwrap(), Status::Converting(_))
+    }
+
+    /// Pick the output folder and name in a save dialog, then convert there
+    ///
+    /// Only this conversion goes there, the output folder setting stays as it is.
+    fn save_as(&mut self) {
+        let Some(output) = self.output_file(&self.effective_settings()) else {
+            return;
+        };
+        let format = self.effective_settings().format;
+        let mut dialog = FileDialog::new()
+            .set_file_name(output.file_name().unwrap_or_default().to_string_lossy())
+            .add_filter(format.extension().to_uppercase(), &[format.extension()]);
+        if let Some(dir) = output.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(picked) = dialog.save_file() else {
+            return;
+        };
+        self.set_output(Some(picked));
+        // The dialog already asked about replacing the file, extra formats still need asking
+        if self.effective_settings().output_formats().len() == 1 {
+            self.convert();
+        } else {
+            self.start_conversion();
+        }
+    }
+
+    fn set_input(&mut self, path: PathBuf) {
+        self.set_output(Some(self.default_output(&path)));
         self.input_path = Some(path.clone());
         self.video_thumbnail = None; // Reset thumbnail when new video is selected
         self.thumbnail_loading = false;
         *self.thumbnail_path.lock().unwrap() = None;
+        *self.media_info.lock().unwrap() = None;
+        *self.input_problem.lock().unwrap() = None;
+        self.trim_start_text.clear();
+        self.trim_end_text.clear();
+        self.audio_track = None;
+        self.all_tracks = false;
+        self.audio_offset_ms = 0;
+        self.tags = Tags::default();
+        self.ringtone_start = 0.0;
+        self.apply_filename_pattern();
         *self.status.lock().unwrap() = Status::Idle;
 
-        // Start async thumbnail extraction
-        self.extract_thumbnail_async(path);
+        // Start async probe and thumbnail extraction, audio files have no frames to grab
+        self.probe_async(path.clone());
+        if !is_audio_file(&path) {
+            self.extract_thumbnail_async(path);
+        }
+    }
+
+    /// Pre-fill tags by matching the input filename against the configured pattern
+    fn apply_filename_pattern(&mut self) {
+        let Some(stem) = self.input_path.as_ref().and_then(|p| p.file_stem()) else {
+            return;
+        };
+        if self.settings.filename_pattern.trim().is_empty() {
+            return;
+        }
+        if let Some(tags) = Tags::from_filename(&stem.to_string_lossy(), &self.settings.filename_pattern) {
+            self.tags = tags;
+        }
+    }
+
+    /// Probe the selected file, recording why it can't be converted when it has no audio or isn't media at all
+    fn probe_async(&self, video_path: PathBuf) {
+        let media_info_arc = Arc::clone(&self.media_info);
+        let problem = Arc::clone(&self.input_problem);
+
+        self.runtime.spawn(async move {
+            match probe::probe(&video_path).await {
+                Ok(info) => {
+                    if info.audio_streams().next().is_none() {
+                        *problem.lock().unwrap() = Some(tr("This file has no audio track to convert"));
+                    }
+                    *media_info_arc.lock().unwrap() = Some(info);
+                }
+                Err(e) => {
+                    println!("Failed to probe {:?}: {}", video_path, e);
+                    let message = match diagnose::diagnose(&e) {
+                        Some(diagnosis) => diagnosis.message,
+                        None => "This isn't a video or audio file FFmpeg can read",
+                    };
+                    *problem.lock().unwrap() = Some(tr(message));
+                }
+            }
+        });
     }
 
     fn extract_thumbnail_async(&mut self, video_path: PathBuf) {
         use std::fs;
 
         let thumbnail_path_arc = Arc::clone(&self.thumbnail_path);
+        let hwaccel = ffmpeg::hwaccel_args(&self.settings);
+        let low_priority = self.settings.low_priority;
         self.thumbnail_loading = true;
 
         self.runtime.spawn(async move {
@@ -146,414 +573,2817 @@ impl App {
             println!("Thumbnail will be saved to: {:?}", thumbnail_file);
 
             // Use FFmpeg to extract thumbnail at 1 second
-            if let Some(video_str) = video_path.to_str() {
-                if let Some(thumb_str) = thumbnail_file.to_str() {
-                    println!("Running FFmpeg command...");
-                    #[cfg(target_os = "windows")]
-                    let result = {
-                        #[allow(unused_imports)]
-                        use std::os::windows::process::CommandExt;
-                        const CREATE_NO_WINDOW: u32 = 0x08000000;
-                        Command::new("ffmpeg")
-                            .args([
-                                "-ss",
-                                "00:00:01",
-                                "-i",
-                                video_str,
-                                "-vframes",
-                                "1",
-                                "-q:v",
-                                "2",
-                                "-y",
-                                thumb_str,
-                            ])
-                            .creation_flags(CREATE_NO_WINDOW)
-                            .output()
-                            .await
-                    };
-                    
-                    #[cfg(not(target_os = "windows"))]
-                    let result = Command::new("ffmpeg")
-                        .args([
-                            "-ss",
-                            "00:00:01",
-                            "-i",
-                            video_str,
-                            "-vframes",
-                            "1",
-                            "-q:v",
-                            "2",
-                            "-y",
-                            thumb_str,
-                        ])
-                        .output()
-                        .await;
-
-                    match result {
-                        Ok(output) => {
-                            println!("FFmpeg exit status: {}", output.status);
-                            if !output.status.success() {
-                                println!("FFmpeg stderr: {}", String::from_utf8_lossy(&output.stderr));
-                            }
-
-                            if output.status.success() && thumbnail_file.exists() {
-                                println!("Thumbnail extracted successfully!");
-                                *thumbnail_path_arc.lock().unwrap() = Some(thumbnail_file);
-                            } else {
-                                println!("Thumbnail file does not exist or FFmpeg failed");
-                            }
-                        }
-                        Err(e) => {
-                            println!("Failed to run FFmpeg: {}", e);
-                        }
+            println!("Running FFmpeg command...");
+            let result = ffmpeg::LOW_PRIORITY
+                .sync_scope(low_priority, ffmpeg::command)
+                .args(&hwaccel)
+                .args(["-ss", "00:00:01", "-i"])
+                .arg(ffmpeg::path_arg(&video_path))
+                .args(["-vframes", "1", "-q:v", "2", "-y"])
+                .arg(ffmpeg::path_arg(&thumbnail_file))
+                .output()
+                .await;
+
+            match result {
+                Ok(output) => {
+                    println!("FFmpeg exit status: {}", output.status);
+                    if !output.status.success() {
+                        println!("FFmpeg stderr: {}", String::from_utf8_lossy(&output.stderr));
+                    }
+
+                    if output.status.success() && thumbnail_file.exists() {
+                        println!("Thumbnail extracted successfully!");
+                        *thumbnail_path_arc.lock().unwrap() = Some(thumbnail_file);
+                    } else {
+                        println!("Thumbnail file does not exist or FFmpeg failed");
                     }
-                } else {
-                    println!("Failed to convert thumbnail path to string");
                 }
-            } else {
-                println!("Failed to convert video path to string");
+                Err(e) => {
+                    println!("Failed to run FFmpeg: {}", e);
+                }
             }
         });
     }
 
-    fn convert(&self) {
-        let input = self.input_path.clone().unwrap();
-        let output = self.output_path.clone().unwrap();
-        let status = Arc::clone(&self.status);
+    /// Per-conversion options from the UI, or a message describing the invalid field
+    fn job_options(&self) -> Result<JobOptions, String> {
+        self.check_encoders(&self.effective_settings())?;
+        let parse_field = |text: &str, name: &str| {
+            if text.trim().is_empty() {
+                Ok(None)
+            } else {
+                timecode::parse(text)
+                    .map(Some)
+                    .ok_or_else(|| tr_args("Invalid {field} time", &[("field", &tr(name))]))
+            }
+        };
 
-        *status.lock().unwrap() = Status::Converting;
+        let duration = self.media_info.lock().unwrap().as_ref().and_then(|m| m.duration);
 
-        self.runtime.spawn(async move {
-            #[cfg(target_os = "windows")]
-            let result = {
-                #[allow(unused_imports)]
-                use std::os::windows::process::CommandExt;
-                const CREATE_NO_WINDOW: u32 = 0x08000000;
-                Command::new("ffmpeg")
-                    .args([
-                        "-i",
-                        input.to_str().unwrap(),
-                        "-vn",
-                        "-acodec",
-                        "libmp3lame",
-                        "-ab",
-                        "192k",
-                        "-y",
-                        output.to_str().unwrap(),
-                    ])
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .output()
-                    .await
-            };
-            
-            #[cfg(not(target_os = "windows"))]
-            let result = Command::new("ffmpeg")
-                .args([
-                    "-i",
-                    input.to_str().unwrap(),
-                    "-vn",
-                    "-acodec",
-                    "libmp3lame",
-                    "-ab",
-                    "192k",
-                    "-y",
-                    output.to_str().unwrap(),
-                ])
-                .output()
-                .await;
+        // Ringtone mode replaces the trim fields with a fixed-length window
+        let (trim_start, trim_end) = if self.ringtone {
+            let end = self.ringtone_start + RINGTONE_LENGTH;
+            (Some(self.ringtone_start), Some(duration.map_or(end, |d| end.min(d))))
+        } else {
+            (
+                parse_field(&self.trim_start_text, "start")?,
+                parse_field(&self.trim_end_text, "end")?,
+            )
+        };
 
-            let new_status = match result {
-                Ok(out) if out.status.success() => Status::Done,
-                Ok(out) => Status::Error(String::from_utf8_lossy(&out.stderr).to_string()),
-                Err(e) => Status::Error(e.to_string()),
-            };
+        if let (Some(start), Some(end)) = (trim_start, trim_end)
+            && start >= end
+        {
+            return Err(tr("Start time must be before end time"));
+        }
 
-            *status.lock().unwrap() = new_status;
-        });
-    }
-}
+        if let (Some(start), Some(duration)) = (trim_start, duration)
+            && start >= duration
+        {
+            return Err(tr("Start time is past the end of the video"));
+        }
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Set dark mode
-        ctx.set_visuals(egui::Visuals::dark());
+        let cover = if self.settings.embed_cover {
+            self.thumbnail_path.lock().unwrap().clone()
+        } else {
+            None
+        };
 
-        // Handle dropped files
-        ctx.input(|i| {
-            if !i.raw.dropped_files.is_empty() {
-                if let Some(path) = i.raw.dropped_files[0].path.clone() {
-                    self.set_input(path);
-                    self.dropped_file = true;
-                }
-            }
-        });
+        let extra_args = shlex::split(&self.settings.extra_args)
+            .ok_or_else(|| tr("Unbalanced quotes in extra arguments"))?;
 
-        // Show info popup window
-        if self.show_info_popup {
-            egui::Window::new("About")
-                .collapsible(false)
-                .resizable(false)
-                .fixed_size(Vec2::new(250.0, 180.0))
-                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-                .show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.add_space(10.0);
-                        ui.label(
-                            egui::RichText::new("Version 1.0")
-                                .size(12.0)
-                                .color(Color32::LIGHT_GRAY),
-                        );
-                        ui.add_space(5.0);
-                        ui.label(
-                            egui::RichText::new("Powered by FFmpeg")
-                                .size(12.0)
-                                .color(Color32::LIGHT_GRAY),
-                        );
-                        ui.add_space(15.0);
-                        if ui.button("Close").clicked() {
-                            self.show_info_popup = false;
-                        }
-                        ui.add_space(10.0);
-                    });
-                });
+        Ok(JobOptions {
+            trim_start,
+            trim_end,
+            audio_track: self.audio_track,
+            cover,
+            tags: self.tags.clone(),
+            extra_args,
+            eq: self.eq,
+            audio_offset_ms: self.audio_offset_ms,
+            ..Default::default()
+        })
+    }
+
+    /// Settings for the next conversion, with ringtone mode applied
+    fn effective_settings(&self) -> Settings {
+        if self.ringtone {
+            return self.settings.ringtone();
         }
+        let mut settings = self.settings.clone();
+        if settings.auto_bitrate
+            && let Some(bitrate) = self.auto_bitrate()
+        {
+            settings.bitrate = bitrate;
+        }
+        settings
+    }
 
-        egui::CentralPanel::default()
-            .show(ctx, |ui| {
-                ui.vertical_centered(|ui| {
-
-                    ui.add_space(20.0);
-
-                    // Drop zone
-                    let drop_zone_size = Vec2::new(250.0, 160.0);
-                    let (rect, response) = ui.allocate_exact_size(drop_zone_size, egui::Sense::click());
-
-                    // Draw dashed border
-                    let painter = ui.painter();
-                    let stroke = Stroke::new(2.0, Color32::GRAY);
-                    let rounding = CornerRadius::same(12);
-
-                    painter.rect_stroke(rect, rounding, stroke, egui::StrokeKind::Outside);
-
-                    // Load and display thumbnail if video is selected
-                    if self.input_path.is_some() {
-                        // Check if thumbnail is ready to load
-                        if self.video_thumbnail.is_none() {
-                            let thumb_path_opt = self.thumbnail_path.lock().unwrap().clone();
-                            if let Some(thumb_path) = thumb_path_opt {
-                                println!("Loading thumbnail from: {:?}", thumb_path);
-                                self.video_thumbnail = self.load_icon_from_file(ctx, thumb_path.to_str().unwrap());
-                                if self.video_thumbnail.is_some() {
-                                    println!("Thumbnail loaded successfully!");
-                                } else {
-                                    println!("Failed to load thumbnail image");
-                                }
-                                self.thumbnail_loading = false;
+    /// Bitrate the "Auto" option picks for the selected audio track
+    fn auto_bitrate(&self) -> Option<u32> {
+        let media_info = self.media_info.lock().unwrap();
+        let stream = media_info.as_ref()?.audio_stream(self.audio_track)?;
+        Some(settings::auto_bitrate(stream))
+    }
+
+    /// Where the converted file goes, with the extension of `settings`' format
+    fn output_file(&self, settings: &Settings) -> Option<PathBuf> {
+        let input = self.input_path.as_ref()?;
+        let output = self.output_path.as_ref()?;
+        Some(pipeline::output_path(input, output, settings.format))
+    }
+
+    /// What ffprobe found out about the input file
+    fn media_info_ui(&self, ui: &mut egui::Ui) {
+        let Some(info) = self.media_info.lock().unwrap().clone() else {
+            return;
+        };
+        egui::CollapsingHeader::new(tr("Media info"))
+            .id_salt("media_info")
+            .show(ui, |ui| {
+                egui::Grid::new("media_info_grid")
+                    .num_columns(2)
+                    .spacing([10.0, 2.0])
+                    .show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(egui::RichText::new(tr(label)).size(11.0).color(Color32::GRAY));
+                            ui.label(egui::RichText::new(value).size(11.0));
+                            ui.end_row();
+                        };
+                        if let Some(duration) = info.duration {
+                            row("Duration", timecode::format(duration));
+                        }
+                        if let Some(ref container) = info.container {
+                            let mut text = container.clone();
+                            if let Some(bitrate) = info.bitrate_kbps {
+                                text += &format!(" · {} kbps", bitrate);
+                            }
+                            row("Container", text);
+                        }
+                        if let Some(video) = info.streams.iter().find(|s| s.codec_type == "video") {
+                            let mut parts = vec![video.codec_name.clone()];
+                            if let (Some(width), Some(height)) = (video.width, video.height) {
+                                parts.push(format!("{}×{}", width, height));
+                            }
+                            if let Some(fps) = video.fps() {
+                                parts.push(format!("{:.2} fps", fps).replace(".00", ""));
+                            }
+                            row("Video", parts.join(" · "));
+                        }
+                        for stream in info.audio_streams() {
+                            let mut text = stream.label();
+                            if let Some(bitrate) = stream.bitrate_kbps() {
+                                text += &format!(" · {} kbps", bitrate);
+                            }
+                            if let Some(rate) = stream.sample_rate() {
+                                text += &format!(" · {:.1} kHz", rate as f32 / 1000.0);
                             }
+                            row("Audio", text);
                         }
+                        let count = |kind: &str| info.streams.iter().filter(|s| s.codec_type == kind).count();
+                        row(
+                            "Streams",
+                            tr_args(
+                                "{total} ({video} video, {audio} audio, {subtitle} subtitle)",
+                                &[
+                                    ("total", &info.streams.len()),
+                                    ("video", &count("video")),
+                                    ("audio", &count("audio")),
+                                    ("subtitle", &count("subtitle")),
+                                ],
+                            ),
+                        );
+                    });
+            });
+    }
 
-                        // Display thumbnail if available
-                        if let Some(ref thumbnail) = self.video_thumbnail {
-                            // Draw thumbnail inside the drop zone with rounded corners
-                            let thumb_rect = rect.shrink(4.0); // Shrink slightly to fit within border
+    /// Look for FFmpeg and its encoders in the background, a download is offered when it's missing
+    fn check_ffmpeg(&self) {
+        let missing = Arc::clone(&self.ffmpeg_missing);
+        let encoders = Arc::clone(&self.encoders);
+        *encoders.lock().unwrap() = None;
+        self.runtime.spawn(async move {
+            if !ffmpeg::is_installed().await {
+                println!("FFmpeg not found");
+                missing.store(download::available(), Ordering::Relaxed);
+                return;
+            }
+            *encoders.lock().unwrap() = ffmpeg::encoders().await;
+        });
+    }
 
-                            // Calculate rounded corners vertices and UVs
-                            let uv_rect = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    /// Whether FFmpeg can write `format`, assumed until the encoders are known
+    fn has_encoder(&self, format: OutputFormat) -> bool {
+        self.encoders
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|encoders| encoders.contains(format.encoder()))
+    }
 
-                            // Simple approach: draw image then mask with rounded rect
-                            // Draw the image first
-                            painter.image(
-                                thumbnail.id(),
-                                thumb_rect,
-                                uv_rect,
-                                Color32::WHITE,
-                            );
+    /// Fail when FFmpeg lacks an encoder for one of `settings`' formats
+    fn check_encoders(&self, settings: &Settings) -> Result<(), String> {
+        match settings.output_formats().into_iter().find(|&format| !self.has_encoder(format)) {
+            Some(format) => Err(tr_args(
+                "Your FFmpeg build has no {encoder} encoder, needed for {format}",
+                &[("encoder", &format.encoder()), ("format", &format.extension().to_uppercase())],
+            )),
+            None => Ok(()),
+        }
+    }
 
-                            // Draw a rounded rect frame to create the rounded corner effect
-                            // by covering the corners with the background color
-                            let bg_color = ui.visuals().window_fill();
-                            painter.rect_stroke(thumb_rect, rounding, Stroke::new(4.0, bg_color), egui::StrokeKind::Outside);
+    /// Offer to download FFmpeg, with its progress once started
+    fn ffmpeg_download_ui(&mut self, ctx: &egui::Context) {
+        if !self.ffmpeg_missing.load(Ordering::Relaxed) {
+            return;
+        }
+        let state = self.ffmpeg_download.lock().unwrap().clone();
+        if state == DownloadState::Done {
+            ffmpeg::set_paths(&self.settings.ffmpeg_path, &self.settings.ffprobe_path);
+            self.ffmpeg_missing.store(false, Ordering::Relaxed);
+            *self.ffmpeg_download.lock().unwrap() = DownloadState::Idle;
+            self.check_ffmpeg();
+            return;
+        }
 
-                            // Optionally: Draw a subtle overlay on hover
-                            if response.hovered() {
-                                painter.rect_filled(thumb_rect, rounding, Color32::from_black_alpha(20));
+        egui::Window::new(tr("FFmpeg not found"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                match state {
+                    DownloadState::Downloading(progress) => {
+                        ui.add(egui::ProgressBar::new(progress).desired_width(250.0).show_percentage());
+                        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+                    }
+                    DownloadState::Extracting => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(tr("Unpacking…"));
+                        });
+                        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+                    }
+                    DownloadState::Idle | DownloadState::Failed(_) | DownloadState::Done => {
+                        ui.label(tr("Converting needs FFmpeg, which isn't installed or couldn't be started."));
+                        ui.label(tr("A static build can be downloaded into the app's data folder and used from then on."));
+                        if let DownloadState::Failed(ref e) = state {
+                            ui.label(egui::RichText::new(e).color(Color32::from_rgb(248, 113, 113)));
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("Download FFmpeg")).clicked() {
+                                download::start(Arc::clone(&self.ffmpeg_download));
                             }
-                        } else {
-                            // Draw play icon when thumbnail is loading or failed
-                            let center = rect.center();
-                            let icon_size = 40.0;
-
-                            // Triangle play button
-                            let points = vec![
-                                egui::pos2(center.x - icon_size * 0.4, center.y - icon_size * 0.5),
-                                egui::pos2(center.x - icon_size * 0.4, center.y + icon_size * 0.5),
-                                egui::pos2(center.x + icon_size * 0.5, center.y),
-                            ];
-                            painter.add(egui::Shape::convex_polygon(
-                                points,
-                                Color32::GRAY,
-                                Stroke::NONE,
-                            ));
-
-                            // Request repaint if still loading
-                            if self.thumbnail_loading {
-                                ctx.request_repaint();
+                            if ui.button(tr("Not now")).clicked() {
+                                self.ffmpeg_missing.store(false, Ordering::Relaxed);
                             }
-                        }
-                    } else {
-                        // Draw play icon when no video selected
-                        let center = rect.center();
-                        let icon_size = 40.0;
-
-                        // Triangle play button
-                        let points = vec![
-                            egui::pos2(center.x - icon_size * 0.4, center.y - icon_size * 0.5),
-                            egui::pos2(center.x - icon_size * 0.4, center.y + icon_size * 0.5),
-                            egui::pos2(center.x + icon_size * 0.5, center.y),
-                        ];
-                        painter.add(egui::Shape::convex_polygon(
-                            points,
-                            Color32::GRAY,
-                            Stroke::NONE,
-                        ));
+                        });
                     }
+                }
+            });
+    }
 
-                    // Change cursor to pointer hand on hover
-                    if response.hovered() {
-                        ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
-                    }
+    /// Outputs of the single conversion that are already on disk
+    fn existing_outputs(&self) -> Vec<PathBuf> {
+        let (Some(input), Some(output)) = (&self.input_path, &self.output_path) else {
+            return Vec::new();
+        };
+        pipeline::existing_outputs(input, output, &self.effective_settings())
+    }
 
-                    if response.clicked() {
-                        if let Some(path) = FileDialog::new()
-                            .add_filter("Video", &["mp4", "mkv", "avi", "mov", "webm", "flv"])
-                            .pick_file()
-                        {
-                            self.set_input(path);
-                        }
-                    }
+    /// Convert, first asking or following the saved choice when that would replace existing files
+    fn start_conversion(&mut self) {
+        if self.existing_outputs().is_empty() {
+            self.convert();
+            return;
+        }
+        match self.settings.existing_output {
+            ExistingOutput::Ask => self.overwrite_prompt = Some(self.existing_outputs()),
+            choice => self.resolve_existing(choice),
+        }
+    }
 
-                    // Status text
-                    let status = self.status.lock().unwrap().clone();
-                    let text = if let Some(ref path) = self.input_path {
-                        path.file_name().unwrap().to_string_lossy().to_string()
-                    } else {
-                        "Drop your video here to convert \n (\"mp4\", \"mkv\", \"avi\", \"mov\", \"webm\", \"flv\")".to_string()
-                    };
+    /// Carry out `choice` for outputs that already exist
+    fn resolve_existing(&mut self, choice: ExistingOutput) {
+        match choice {
+            ExistingOutput::Ask => {}
+            ExistingOutput::Overwrite => self.convert(),
+            ExistingOutput::Rename => {
+                self.rename_output();
+                self.convert();
+            }
+            ExistingOutput::Skip => println!("Skipped, the output already exists"),
+        }
+    }
 
+    /// Number the output name, `song (1)`, `song (2)` and so on, until no output exists under it
+    fn rename_output(&mut self) {
+        let (Some(input), Some(output)) = (&self.input_path, &self.output_path) else {
+            return;
+        };
+        let renamed = pipeline::numbered_output(input, output, &self.effective_settings());
+        self.set_output(Some(renamed));
+    }
 
-                    let text_color = match &status {
-                        Status::Done => Color32::from_rgb(74, 222, 128),
-                        Status::Error(_) => Color32::from_rgb(248, 113, 113),
-                        _ => Color32::LIGHT_GRAY,
-                    };
+    fn convert(&self) {
+        let input = self.input_path.clone().unwrap();
+        let settings = self.effective_settings();
+        let output = self.output_file(&settings).unwrap();
+        let Ok(job) = self.job_options() else {
+            return;
+        };
+        let media_info = self.media_info.lock().unwrap().clone();
+        let status = Arc::clone(&self.status);
 
-                    let display_text = match &status {
-                        Status::Converting => "Converting...".to_string(),
-                        Status::Done => "Done!".to_string(),
-                        Status::Error(_) => "Error occurred".to_string(),
-                        _ => text,
-                    };
+        *status.lock().unwrap() = Status::Converting(Progress::default());
+        self.cancel_conversion.store(false, Ordering::Relaxed);
+        let cancel = Arc::clone(&self.cancel_conversion);
 
-                    ui.add_space(20.0);
-                    // Status text with optional link icon (centered)
-                    ui.vertical_centered(|ui| {
-                    if matches!(status, Status::Done) {
-                        // When done, use horizontal for text + icon
-                        ui.horizontal(|ui| {
-                            ui.add_space((ui.available_width() - 100.0) / 2.0); // Approximate centering
-                            ui.label(
-                                egui::RichText::new(&display_text)
-                                    .size(11.0)
-                                    .color(text_color),
-                            );
+        let all_tracks = self.all_tracks;
 
-                            if let Some(ref output_path) = self.output_path {
-                                ui.add_space(5.0);
-                                let link_btn = ui.add(
-                                    egui::Button::new(egui::RichText::new("📂").size(14.0)).frame(false),
-                                );
+        self.runtime.spawn(async move {
+            let on_progress = |progress: Progress| {
+                if let Status::Converting(ref mut current) = *status.lock().unwrap() {
+                    *current = progress;
+                }
+            };
+            let started = std::time::SystemTime::now();
+            let conversion = async {
+                match media_info {
+                    Some(ref media) if all_tracks => {
+                        pipeline::run_all_tracks(&input, &output, &settings, job, media, &on_progress).await
+                    }
+                    _ => {
+                        converter::for_settings(&settings)
+                            .convert(&input, &output, &settings, job, media_info.as_ref(), &on_progress)
+                            .await
+                    }
+                }
+            };
+            // Dropping the conversion when it is cancelled kills FFmpeg
+            let cancelled = async {
+                while !cancel.load(Ordering::Relaxed) {
+                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                }
+            };
+            let result = tokio::select! {
+                result = ffmpeg::LOW_PRIORITY.scope(
+                    settings.low_priority,
+                    pipeline::STALL_TIMEOUT.scope(settings.stall_timeout, conversion),
+                ) => result,
+                _ = cancelled => {
+                    println!("Cancelled '{}'", input.display());
+                    let tracks = media_info.as_ref().filter(|_| all_tracks);
+                    pipeline::remove_outputs(&input, &output, &settings, tracks, started);
+                    *status.lock().unwrap() = Status::Idle;
+                    return;
+                }
+            };
 
-                                if link_btn.hovered() {
-                                    ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
-                                }
+            if let Ok(conversion) = &result {
+                if settings.preserve_timestamps {
+                    pipeline::copy_timestamps(&input, &conversion.outputs);
+                }
+                pipeline::dispose_source(&input, conversion, settings.source_action);
+            }
+            let new_status = match result {
+                Ok(Conversion { outputs, warning: None }) => {
+                    Status::Done(outputs, started.elapsed().unwrap_or_default())
+                }
+                Ok(Conversion { outputs, warning: Some(warning) }) => Status::Suspicious(outputs, warning),
+                Err(e) => Status::Error(e),
+            };
 
-                                if link_btn.clicked() {
-                                    #[cfg(target_os = "windows")]
-                                    {
-                                        #[allow(unused_imports)]
-                                        use std::os::windows::process::CommandExt;
-                                        const CREATE_NO_WINDOW: u32 = 0x08000000;
-                                        let _ = std::process::Command::new("explorer")
-                                            .args(["/select,", output_path.to_str().unwrap()])
-                                            .creation_flags(CREATE_NO_WINDOW)
-                                            .spawn();
-                                    }
-                                    #[cfg(target_os = "macos")]
-                                    {
-                                        let _ = std::process::Command::new("open")
-                                            .args(["-R", output_path.to_str().unwrap()])
-                                            .spawn();
-                                    }
-                                    #[cfg(target_os = "linux")]
-                                    {
-                                        if let Some(parent) = output_path.parent() {
-                                            let _ = std::process::Command::new("xdg-open").arg(parent).spawn();
-                                        }
-                                    }
-                                }
+            *status.lock().unwrap() = new_status;
+        });
+    }
 
-                                link_btn.on_hover_text("Open file location");
+    /// Add files to the batch queue, moving the file open in the editor there too
+    ///
+    /// Folders are searched recursively for supported files.
+    /// Files already waiting or converting are skipped with a warning.
+    fn enqueue(&mut self, paths: Vec<PathBuf>) {
+        let mut added = Vec::new();
+        if let Some(input) = self.input_path.take() {
+            added.push(QueueItem::new(input));
+            self.set_output(None);
+            self.video_thumbnail = None;
+            *self.media_info.lock().unwrap() = None;
+        }
+        for path in paths {
+            if playlist::is_playlist(&path) {
+                match playlist::read(&path) {
+                    Ok(entries) => {
+                        let len = entries.len();
+                        let items = entries.into_iter().enumerate().filter_map(|(n, entry)| {
+                            if entry.is_file() {
+                                Some(QueueItem::in_playlist(entry, n + 1, len))
+                            } else {
+                                println!("Playlist entry '{}' not found", entry.display());
+                                None
                             }
                         });
-                    } else {
-                        // Simple centered label when not done
-                        ui.label(
-                            egui::RichText::new(&display_text)
-                                .size(11.0)
-                                .color(text_color),
-                        );
+                        added.extend(items);
                     }
-});
-
-                    ui.add_space(20.0);
-
-                    // Bottom bar
-                    ui.horizontal(|ui| {
+                    Err(e) => println!("Failed to read playlist '{}': {}", path.display(), e),
+                }
+            } else if path.is_dir() {
+                let files = queue::scan_folder(&path, &is_supported_file);
+                println!("Found {} files in {}", files.len(), path.display());
+                added.extend(files.into_iter().map(|file| QueueItem::in_folder(file, &path)));
+            } else {
+                added.push(QueueItem::new(path));
+            }
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        let mut skipped = Vec::new();
+        for item in added {
+            if queue::is_duplicate(&queue, &item.input) {
+                println!("Skipping '{}', it is already in the queue", item.input.display());
+                skipped.push(item.input.file_name().unwrap_or_default().to_string_lossy().to_string());
+            } else {
+                queue.push(item);
+            }
+        }
+        self.queue_warning = match skipped.len() {
+            0 => None,
+            1 => Some(tr_args("Skipped {name}, it is already in the queue", &[("name", &skipped[0])])),
+            n => Some(tr_args("Skipped {count} files already in the queue: {names}", &[("count", &n), ("names", &skipped.join(", "))])),
+        };
+    }
+
+    /// Open dropped or command line files and start the queue if `auto`
+    ///
+    /// A single file opens in the editor, anything more goes to the queue.
+    fn open_paths(&mut self, paths: Vec<PathBuf>, auto: bool) {
+        if paths.is_empty() {
+            return;
+        }
+        let single_file = paths.len() == 1 && paths[0].is_file() && !playlist::is_playlist(&paths[0]);
+        if !auto && single_file && self.queue.lock().unwrap().is_empty() {
+            self.set_input(paths.into_iter().next().unwrap());
+            return;
+        }
+        self.enqueue(paths);
+        if auto {
+            self.convert_queue();
+        }
+    }
+
+    /// Overall progress of the single conversion or the queue, for the taskbar
+    fn taskbar_state(&self) -> TaskbarState {
+        let items = self.queue.lock().unwrap().clone();
+        if queue::is_running(&self.queue) {
+            // Finished files count whole, running ones by their progress
+            let done: f32 = items
+                .iter()
+                .map(|item| match item.state {
+                    ItemState::Waiting => 0.0,
+                    ItemState::Converting => item.progress,
+                    _ => 1.0,
+                })
+                .sum();
+            return TaskbarState::Progress(done / items.len() as f32);
+        }
+        match *self.status.lock().unwrap() {
+            Status::Converting(progress) => TaskbarState::Progress(progress.fraction),
+            Status::Error(_) => TaskbarState::Error,
+            _ if items.iter().any(|item| matches!(item.state, ItemState::Failed(_))) => TaskbarState::Error,
+            _ => TaskbarState::Idle,
+        }
+    }
+
+    /// Queue the files on the clipboard, `text` is what was pasted if there was any
+    fn paste_files(&mut self, text: Option<&str>) {
+        let paths = clipboard_paths(text);
+        if paths.is_empty() {
+            println!("No files on the clipboard");
+            return;
+        }
+        self.enqueue(paths);
+    }
+
+    /// Whether the tray shows work in progress or finished, following the taskbar
+    fn tray_state(&self) -> TrayState {
+        if let TaskbarState::Progress(progress) = self.taskbar_state() {
+            return TrayState::Converting(progress);
+        }
+        let items = self.queue.lock().unwrap();
+        let finished = |state: &ItemState| matches!(state, ItemState::Done(_) | ItemState::Suspicious(..));
+        match *self.status.lock().unwrap() {
+            Status::Done(..) | Status::Suspicious(..) => TrayState::Done,
+            _ if !items.is_empty() && items.iter().all(|item| finished(&item.state)) => TrayState::Done,
+            _ => TrayState::Idle,
+        }
+    }
+
+    /// What is being converted, for progress notifications
+    fn progress_subject(&self) -> String {
+        if queue::is_running(&self.queue) {
+            let items = self.queue.lock().unwrap();
+            let finished = items
+                .iter()
+                .filter(|item| !matches!(item.state, ItemState::Waiting | ItemState::Converting))
+                .count();
+            return tr_args("{finished} of {total} files finished", &[("finished", &finished), ("total", &items.len())]);
+        }
+        self.input_path
+            .as_ref()
+            .map(|p| p.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Carry out what was picked from the tray menu
+    fn handle_tray(&mut self, ctx: &egui::Context) {
+        let Some(ref tray) = self.tray else {
+            return;
+        };
+        for command in tray.commands() {
+            match command {
+                TrayCommand::TogglePause if queue::is_running(&self.queue) => {
+                    self.queue_paused.fetch_xor(true, Ordering::Relaxed);
+                }
+                TrayCommand::TogglePause => self.convert_queue(),
+                TrayCommand::Quit => {
+                    self.quitting = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+
+        let running = queue::is_running(&self.queue);
+        let waiting = self.queue.lock().unwrap().iter().any(|item| matches!(item.state, ItemState::Waiting));
+        let paused = if running {
+            Some(self.queue_paused.load(Ordering::Relaxed))
+        } else {
+            waiting.then_some(true)
+        };
+        let state = self.tray_state();
+        if let Some(ref mut tray) = self.tray {
+            tray.set(state, paused);
+        }
+
+        // Closing or minimizing the window while something converts hides it to the tray instead
+        let busy = running || matches!(*self.status.lock().unwrap(), Status::Converting(_));
+        let (close, minimized, focused) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.close_requested(), viewport.minimized == Some(true), viewport.focused == Some(true))
+        });
+        if close && busy && !self.quitting {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        }
+        if busy && !self.hidden_to_tray && (close && !self.quitting || minimized) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+            self.hidden_to_tray = true;
+            self.milestone = match state {
+                TrayState::Converting(progress) => (progress * 4.0) as u8,
+                _ => 0,
+            };
+        } else if focused {
+            self.hidden_to_tray = false;
+        }
+
+        // Every quarter of the way is announced while the window is out of sight, the end by the finish notification
+        if self.hidden_to_tray
+            && let TrayState::Converting(progress) = state
+        {
+            let quarter = (progress * 4.0) as u8;
+            if quarter > self.milestone && quarter < 4 && self.settings.notifications {
+                notify::show(tr_args("Converting: {percent}% done", &[("percent", &(quarter as u32 * 25))]), self.progress_subject(), None);
+            }
+            self.milestone = quarter;
+        }
+    }
+
+    /// Start converting the waiting queue items in the background
+    fn convert_queue(&mut self) {
+        let Ok(job) = self.batch_job_options() else {
+            return;
+        };
+        self.scheduled_start = None;
+        self.queue_paused.store(false, Ordering::Relaxed);
+        // Each worker claims the next waiting item and its output paths for itself
+        for _ in 0..self.settings.parallel_jobs.max(1) {
+            self.runtime.spawn(queue::run(
+                Arc::clone(&self.queue),
+                Arc::clone(&self.queue_paused),
+                self.settings.clone(),
+                job.clone(),
+                self.batch_output_dir.clone().or_else(|| self.settings.output_dir.clone()),
+            ));
+        }
+    }
+
+    /// Job options shared by every queue item, per-file ones are left at their defaults
+    fn batch_job_options(&self) -> Result<JobOptions, String> {
+        self.check_encoders(&self.settings)?;
+        let mut extra_args = shlex::split(&self.settings.extra_args)
+            .ok_or_else(|| tr("Unbalanced quotes in extra arguments"))?;
+        if self.settings.low_impact && self.settings.low_impact_threads > 0 {
+            extra_args.push("-threads".to_string());
+            extra_args.push(self.settings.low_impact_threads.to_string());
+        }
+        Ok(JobOptions {
+            extra_args,
+            eq: self.eq,
+            ..Default::default()
+        })
+    }
+
+    fn queue_ui(&mut self, ui: &mut egui::Ui) {
+        let items = self.queue.lock().unwrap().clone();
+        if items.is_empty() {
+            return;
+        }
+
+        ui.add_space(10.0);
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_width(content_width(ui));
+            // A third of the window, so taller windows show more of a long batch
+            let max_height = (ui.ctx().content_rect().height() / 3.0).max(120.0);
+            egui::ScrollArea::vertical().max_height(max_height).show(ui, |ui| {
+                // Finished files collapse into one summary row to keep long batches readable
+                let hide_finished = self.settings.hide_finished;
+                let hidden = |item: &QueueItem| hide_finished && matches!(item.state, ItemState::Done(_));
+                let finished = items.iter().filter(|item| hidden(item)).count();
+                if finished > 0 {
+                    ui.label(
+                        egui::RichText::new(tr_args("✔ {count} done", &[("count", &finished)]))
+                            .size(11.0)
+                            .color(Color32::from_rgb(74, 222, 128)),
+                    );
+                }
+                for (index, item) in items.iter().enumerate() {
+                    if hidden(item) {
+                        continue;
+                    }
+                    let (icon, color) = match item.state {
+                        ItemState::Waiting if item.high_priority => ("⚡", Color32::LIGHT_YELLOW),
+                        ItemState::Waiting => ("⏳", secondary_text(ui)),
+                        ItemState::Converting => ("⏵", Color32::LIGHT_BLUE),
+                        ItemState::Done(_) => ("✔", Color32::from_rgb(74, 222, 128)),
+                        ItemState::Suspicious(..) => ("⚠", Color32::from_rgb(251, 191, 36)),
+                        ItemState::Failed(_) => ("✖", Color32::from_rgb(248, 113, 113)),
+                        ItemState::Skipped => ("⏭", Color32::GRAY),
+                    };
+                    let name = item.input.file_name().unwrap_or_default().to_string_lossy();
+                    let row = ui
+                        .horizontal(|ui| {
+                            let pending = matches!(item.state, ItemState::Waiting | ItemState::Converting);
+                            if pending
+                                && icon_hint(ui.small_button("✖"), &tr("Remove from the queue, stopping its conversion")).clicked()
+                            {
+                                queue::cancel(&self.queue, item.id);
+                            }
+                            if let ItemState::Waiting = item.state
+                                && icon_hint(
+                                    ui.small_button(if item.overrides.is_empty() { "⚙" } else { "✎" }),
+                                    &tr("Settings for this file only"),
+                                )
+                                .clicked()
+                            {
+                                self.editing_item = (self.editing_item != Some(item.id)).then_some(item.id);
+                            }
+                            if let ItemState::Converting = item.state {
+                                ui.add(
+                                    egui::ProgressBar::new(item.progress)
+                                        .desired_height(14.0)
+                                        .text(
+                                            egui::RichText::new(format!("{} {:.0}%", name, item.progress * 100.0))
+                                                .size(11.0),
+                                        ),
+                                )
+                            } else if let ItemState::Waiting = item.state {
+                                // Waiting files can be dragged onto another row to take its place
+                                let text = egui::RichText::new(format!("{} {}", icon, name)).size(11.0).color(color);
+                                ui.dnd_drag_source(egui::Id::new(("queue_item", item.id)), item.id, |ui| ui.label(text))
+                                    .response
+                            } else {
+                                ui.label(egui::RichText::new(format!("{} {}", icon, name)).size(11.0).color(color))
+                            }
+                        });
+                    if let Some(dragged) = row.response.dnd_release_payload::<u64>() {
+                        queue::move_item(&self.queue, *dragged, index);
+                    }
+                    let label = row.inner;
+                    // Read out as the file name and its state instead of the status symbol
+                    if !matches!(item.state, ItemState::Converting) {
+                        let text = format!("{}, {}", name, item_state_name(item));
+                        label.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Label, true, &text));
+                    }
+                    if let ItemState::Waiting = item.state {
+                        label.context_menu(|ui| {
+                            let moves = [
+                                ("⤒ Move to top", 0),
+                                ("⏶ Move up", index.saturating_sub(1)),
+                                ("⏷ Move down", index + 1),
+                                ("⤓ Move to bottom", items.len()),
+                            ];
+                            for (text, to) in moves {
+                                if ui.button(tr(text)).clicked() {
+                                    queue::move_item(&self.queue, item.id, to);
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            let mut high_priority = item.high_priority;
+                            if ui
+                                .checkbox(&mut high_priority, tr("⚡ High priority"))
+                                .on_hover_text(tr("Convert before the other waiting files, without stopping running ones"))
+                                .changed()
+                                && let Some(entry) = self.queue.lock().unwrap().iter_mut().find(|e| e.id == item.id)
+                            {
+                                entry.high_priority = high_priority;
+                            }
+                        });
+                    }
+                    let file_list = |outputs: &[PathBuf]| {
+                        outputs
+                            .iter()
+                            .map(|p| p.file_name().unwrap_or_default().to_string_lossy().to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    };
+                    match item.state {
+                        ItemState::Done(ref outputs) => {
+                            label.on_hover_text(file_list(outputs));
+                        }
+                        ItemState::Suspicious(ref outputs, ref message) => {
+                            label.on_hover_text(format!("{}\n\n{}", message, file_list(outputs)));
+                        }
+                        ItemState::Failed(ref message) => {
+                            label.on_hover_text(match diagnose::diagnose(message) {
+                                Some(diagnosis) => format!("{}\n{}", diagnosis.message, diagnosis.fix),
+                                None => message.clone(),
+                            });
+                        }
+                        ItemState::Waiting => {
+                            label.on_hover_text(tr("Drag onto another file or right-click to change the order or priority"));
+                        }
+                        ItemState::Skipped => {
+                            label.on_hover_text(tr("Skipped, the output already exists"));
+                        }
+                        ItemState::Converting => {}
+                    }
+                    if self.editing_item == Some(item.id) && matches!(item.state, ItemState::Waiting) {
+                        self.overrides_ui(ui, item);
+                    }
+                }
+            });
+        });
+
+        let warning_text = self.queue_warning.as_ref().map(|warning| {
+            egui::RichText::new(format!("⚠ {}", warning))
+                .size(11.0)
+                .color(Color32::from_rgb(251, 191, 36))
+        });
+        if let Some(text) = warning_text
+            && ui
+                .add(egui::Label::new(text).sense(egui::Sense::click()))
+                .on_hover_text(tr("Click to dismiss"))
+                .clicked()
+        {
+            self.queue_warning = None;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Names"));
+            ui.add(egui::TextEdit::singleline(&mut self.settings.batch_template).hint_text("{stem}").desired_width(190.0))
+                .on_hover_text(tr("Output name for each file: {stem}, {index}, {bitrate} and {format}, e.g. {index:02} - {stem}"));
+        });
+        // Names the first waiting files would get
+        let settings = &self.settings;
+        let preview: Vec<String> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| matches!(item.state, ItemState::Waiting))
+            .take(3)
+            .map(|(index, item)| {
+                let name = queue::output_name(item, index + 1, settings);
+                format!("{}.{}", name, settings.format.extension())
+            })
+            .collect();
+        for name in preview {
+            ui.label(egui::RichText::new(format!("→ {}", name)).size(11.0).color(Color32::GRAY));
+        }
+
+        ui.horizontal(|ui| {
+            match self.scheduled_start {
+                Some(start) => {
+                    ui.label(
+                        egui::RichText::new(tr_args("⏰ Starts at {time}", &[("time", &start.format("%H:%M"))]))
+                            .size(11.0)
+                            .color(Color32::LIGHT_BLUE),
+                    );
+                    if ui.small_button(tr("Cancel")).clicked() {
+                        self.scheduled_start = None;
+                    }
+                }
+                None => {
+                    ui.label(tr("Start at"));
+                    ui.add(egui::TextEdit::singleline(&mut self.schedule_text).hint_text("02:00").desired_width(45.0))
+                        .on_hover_text(tr("Time of day to start the batch, 24-hour hh:mm"));
+                    let start = queue::schedule_time(&self.schedule_text);
+                    if icon_hint(
+                        ui.add_enabled(start.is_some() && !queue::is_running(&self.queue), egui::Button::new("⏰").small()),
+                        &tr("Convert the waiting files at this time, the app has to stay open"),
+                    )
+                    .clicked()
+                    {
+                        self.scheduled_start = start;
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(tr("When finished"));
+            egui::ComboBox::from_id_salt("finish_action")
+                .width(140.0)
+                .selected_text(tr(self.finish_action.name()))
+                .show_ui(ui, |ui| {
+                    for action in FinishAction::ALL {
+                        ui.selectable_value(&mut self.finish_action, action, tr(action.name()));
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.settings.low_impact, tr("Low impact"))
+                .on_hover_text(tr("Run the batch at below-normal priority with fewer encoder threads, so the computer stays usable"));
+            ui.add_enabled(
+                self.settings.low_impact,
+                egui::DragValue::new(&mut self.settings.low_impact_threads)
+                    .range(0..=16)
+                    .custom_formatter(|n, _| if n == 0.0 { tr("auto") } else { tr_args("{count} threads", &[("count", &n)]) }),
+            )
+            .on_hover_text(tr("Encoder threads per file, auto lets FFmpeg use every core"));
+        });
+
+        ui.checkbox(&mut self.settings.hide_finished, tr("Collapse finished files"))
+            .on_hover_text(tr("Show converted files as a single summary row"));
+
+        if items.iter().any(|i| i.playlist_position.is_some()) {
+            ui.checkbox(&mut self.settings.number_playlist, tr("Number files in playlist order"))
+                .on_hover_text(tr("Prefix each output with its playlist position, e.g. \"03 - \""));
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_space((ui.available_width() - 200.0) / 2.0);
+            let done = items.iter().filter(|i| !matches!(i.state, ItemState::Waiting | ItemState::Converting)).count();
+            ui.label(egui::RichText::new(tr_args("{done} of {total} done", &[("done", &done), ("total", &items.len())])).size(11.0).color(Color32::GRAY));
+            let output_text = match self.batch_output_dir.as_ref().or(self.settings.output_dir.as_ref()) {
+                Some(dir) => tr_args("Output: {folder}", &[("folder", &dir.file_name().unwrap_or_default().to_string_lossy())]),
+                None => tr("Output: next to sources"),
+            };
+            if icon_hint(
+                ui.small_button("📁"),
+                &format!("{}\n{}", output_text, tr("Pick a folder to collect the outputs, keeping the layout of dropped folders")),
+            )
+                .clicked()
+            {
+                self.batch_output_dir = FileDialog::new().pick_folder();
+            }
+            let running = queue::is_running(&self.queue);
+            let paused = self.queue_paused.load(Ordering::Relaxed);
+            let waiting = items.iter().any(|i| matches!(i.state, ItemState::Waiting));
+            if running && !paused {
+                if icon_hint(ui.small_button("⏸"), &tr("Pause after the files being converted now")).clicked()
+                {
+                    self.queue_paused.store(true, Ordering::Relaxed);
+                }
+            } else if paused && waiting {
+                // Resuming while the last files still finish would start extra workers
+                if icon_hint(ui.add_enabled(!running, egui::Button::new("⏵").small()), &tr("Resume the queue"))
+                    .on_disabled_hover_text(tr("Pausing after the files being converted now"))
+                    .clicked()
+                {
+                    self.convert_queue();
+                }
+            }
+            let failed = items.iter().filter(|i| matches!(i.state, ItemState::Failed(_))).count();
+            if failed > 0
+                && icon_hint(ui.small_button("↻"), &tr_args("Retry {count} failed files", &[("count", &failed)])).clicked()
+            {
+                println!("Retrying {} failed files", queue::retry_failed(&self.queue));
+            }
+            if ui
+                .add_enabled(!running, egui::Button::new(tr("Clear")))
+                .on_hover_text(tr("Remove every file from the queue"))
+                .clicked()
+            {
+                self.queue.lock().unwrap().clear();
+                queue::discard_saved();
+            }
+        });
+    }
+
+    /// Per-file bitrate, trim and name for a waiting queue item
+    fn overrides_ui(&mut self, ui: &mut egui::Ui, item: &QueueItem) {
+        let mut overrides = item.overrides.clone();
+        egui::Grid::new(("overrides", item.id)).num_columns(2).show(ui, |ui| {
+            ui.label(tr("Bitrate"));
+            let bitrate_text = |bitrate: Option<u32>| match bitrate {
+                Some(bitrate) => format!("{} kbps", bitrate),
+                None => tr("Batch setting"),
+            };
+            egui::ComboBox::from_id_salt(("override_bitrate", item.id))
+                .width(120.0)
+                .selected_text(bitrate_text(overrides.bitrate))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut overrides.bitrate, None, bitrate_text(None));
+                    for bitrate in BITRATES {
+                        ui.selectable_value(&mut overrides.bitrate, Some(bitrate), bitrate_text(Some(bitrate)));
+                    }
+                });
+            ui.end_row();
+
+            ui.label(tr("Trim"));
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut overrides.trim_start).hint_text("00:00").desired_width(55.0))
+                    .on_hover_text(tr("Start time (hh:mm:ss, mm:ss or seconds)"));
+                ui.label("–");
+                ui.add(egui::TextEdit::singleline(&mut overrides.trim_end).hint_text(tr("end")).desired_width(55.0))
+                    .on_hover_text(tr("End time (hh:mm:ss, mm:ss or seconds)"));
+            });
+            ui.end_row();
+
+            ui.label(tr("Name"));
+            let stem = item.input.file_stem().unwrap_or_default().to_string_lossy();
+            ui.add(egui::TextEdit::singleline(&mut overrides.output_name).hint_text(stem).desired_width(120.0))
+                .on_hover_text(tr("Output filename without extension"));
+            ui.end_row();
+        });
+
+        if overrides != item.overrides
+            && let Some(entry) = self.queue.lock().unwrap().iter_mut().find(|entry| entry.id == item.id)
+        {
+            entry.overrides = overrides;
+        }
+    }
+
+    /// Build the commands `convert` would run without running them
+    fn preview_command(&mut self) {
+        let settings = self.effective_settings();
+        let (Some(input), Some(output), Ok(job)) = (&self.input_path, self.output_file(&settings), self.job_options())
+        else {
+            return;
+        };
+        let media_info = self.media_info.lock().unwrap().clone();
+        let commands = pipeline::preview(input, &output, &settings, job, media_info.as_ref());
+        self.command_preview = Some(commands.join("\n\n"));
+    }
+
+    /// Estimated output size in bytes, from the source duration and the bitrate
+    fn estimated_size(&self) -> Option<u64> {
+        let job = self.job_options().ok()?;
+        let settings = self.effective_settings();
+        let media_info = self.media_info.lock().unwrap();
+        let duration = ffmpeg::output_duration(&settings, &job, media_info.as_ref())?;
+        Some((duration * settings.average_bitrate() as f64 * 1000.0 / 8.0) as u64)
+    }
+
+    /// Bitrate to suggest instead when the selected one exceeds what the lossy source holds
+    fn wasted_bitrate_fix(&self) -> Option<u32> {
+        let settings = self.effective_settings();
+        if settings.auto_bitrate || settings.format == OutputFormat::Flac {
+            return None;
+        }
+        let media_info = self.media_info.lock().unwrap();
+        let stream = media_info.as_ref()?.audio_stream(self.audio_track)?;
+        if stream.is_lossless() {
+            return None;
+        }
+        let suggested = settings::matching_bitrate(stream.bitrate_kbps()?);
+        (settings.average_bitrate() > suggested).then_some(suggested)
+    }
+
+    /// Add a preset picked from a file and switch to it, replacing a saved one of the same name
+    fn import_preset(&mut self) {
+        let Some(path) = FileDialog::new().add_filter(tr("Preset"), &["json"]).pick_file() else {
+            return;
+        };
+        match Preset::load(&path) {
+            Ok(preset) => {
+                preset.apply(&mut self.settings);
+                self.settings.custom_presets.retain(|p| p.name != preset.name);
+                self.settings.custom_presets.push(preset);
+            }
+            Err(e) => println!("Failed to import preset '{}': {}", path.display(), e),
+        }
+    }
+
+    /// Store the current settings in the active profile, then load `name`, or keep them as they are for `None`
+    fn switch_profile(&mut self, name: Option<String>) {
+        if let Some(ref active) = self.settings.active_profile {
+            let current = Profile::from_settings(active, &self.settings);
+            if let Some(profile) = self.settings.profiles.iter_mut().find(|p| &p.name == active) {
+                *profile = current;
+            }
+        }
+        if let Some(profile) = name.as_ref().and_then(|name| self.settings.profiles.iter().find(|p| &p.name == name)) {
+            profile.clone().apply(&mut self.settings);
+            if let Some(input) = self.input_path.clone() {
+                self.set_output(Some(self.default_output(&input)));
+            }
+        }
+        self.settings.active_profile = name;
+    }
+
+    /// Profile picker in the top-left corner
+    fn profile_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if let Some(ref mut name) = self.new_profile_name {
+                ui.add(egui::TextEdit::singleline(name).hint_text(tr("Profile name")).desired_width(100.0));
+                let name = name.trim().to_string();
+                if ui.add_enabled(!name.is_empty(), egui::Button::new(tr("Save"))).clicked() {
+                    self.switch_profile(None);
+                    self.settings.profiles.retain(|p| p.name != name);
+                    self.settings.profiles.push(Profile::from_settings(&name, &self.settings));
+                    self.settings.active_profile = Some(name);
+                    self.new_profile_name = None;
+                } else if icon_hint(ui.button("✖"), &tr("Cancel")).clicked() {
+                    self.new_profile_name = None;
+                }
+                return;
+            }
+
+            let active = self.settings.active_profile.clone();
+            let mut picked = None;
+            egui::ComboBox::from_id_salt("profile")
+                .width(110.0)
+                .selected_text(active.clone().unwrap_or_else(|| tr("No profile")))
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(active.is_none(), tr("No profile")).clicked() {
+                        picked = Some(None);
+                    }
+                    for profile in &self.settings.profiles {
+                        if ui.selectable_label(active.as_ref() == Some(&profile.name), &profile.name).clicked() {
+                            picked = Some(Some(profile.name.clone()));
+                        }
+                    }
+                    ui.separator();
+                    if ui
+                        .selectable_label(false, tr("New profile…"))
+                        .on_hover_text(tr("Save the current preset, output folder and naming template as a profile"))
+                        .clicked()
+                    {
+                        self.new_profile_name = Some(String::new());
+                    }
+                    if let Some(ref name) = active
+                        && ui.selectable_label(false, tr_args("Delete \"{name}\"", &[("name", name)])).clicked()
+                    {
+                        self.settings.profiles.retain(|p| &p.name != name);
+                        self.settings.active_profile = None;
+                    }
+                })
+                .response
+                .on_hover_text(tr("Switch between workflows, each with its own preset, output folder and naming template"));
+            if let Some(name) = picked
+                && name != active
+            {
+                self.switch_profile(name);
+            }
+        });
+    }
+
+    fn preset_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add_space((ui.available_width() - 210.0) / 2.0);
+            ui.label(egui::RichText::new(tr("Preset")).size(12.0).color(secondary_text(ui)));
+
+            if let Some(ref mut name) = self.new_preset_name {
+                ui.add(egui::TextEdit::singleline(name).hint_text(tr("Preset name")).desired_width(100.0));
+                let name = name.trim().to_string();
+                if ui.add_enabled(!name.is_empty(), egui::Button::new(tr("Save"))).clicked() {
+                    let preset = Preset::from_settings(&name, &self.settings);
+                    self.settings.custom_presets.retain(|p| p.name != name);
+                    self.settings.custom_presets.push(preset);
+                    self.new_preset_name = None;
+                } else if icon_hint(ui.button("✖"), &tr("Cancel")).clicked() {
+                    self.new_preset_name = None;
+                }
+                return;
+            }
+
+            let presets = self.settings.presets();
+            let active = presets.iter().find(|p| p.matches(&self.settings)).map(|p| p.name.clone());
+            egui::ComboBox::from_id_salt("preset")
+                .width(100.0)
+                .selected_text(active.clone().unwrap_or_else(|| tr("Custom")))
+                .show_ui(ui, |ui| {
+                    for preset in &presets {
+                        if ui.selectable_label(active.as_ref() == Some(&preset.name), &preset.name).clicked() {
+                            preset.apply(&mut self.settings);
+                        }
+                    }
+                    ui.separator();
+                    if ui.selectable_label(false, tr("Import…")).on_hover_text(tr("Add a preset from a file")).clicked() {
+                        self.import_preset();
+                    }
+                    if let Some(preset) = presets.iter().find(|p| active.as_ref() == Some(&p.name))
+                        && ui
+                            .selectable_label(false, tr_args("Export \"{name}\"…", &[("name", &preset.name)]))
+                            .on_hover_text(tr("Save this preset to a file to share it"))
+                            .clicked()
+                    {
+                        export_preset(preset);
+                    }
+                });
+
+            if icon_hint(ui.button("💾"), &tr("Save the current settings as a preset")).clicked() {
+                self.new_preset_name = Some(String::new());
+            }
+            let is_custom = active
+                .as_ref()
+                .is_some_and(|name| self.settings.custom_presets.iter().any(|p| &p.name == name));
+            if is_custom && icon_hint(ui.button("🗑"), &tr("Delete this preset")).clicked() {
+                self.settings.custom_presets.retain(|p| Some(&p.name) != active.as_ref());
+            }
+        });
+    }
+
+    fn tags_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(tr("Tags"))
+            .show(ui, |ui| {
+                egui::Grid::new("tags")
+                    .num_columns(2)
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        // Hint at what ends up in the file when a field is left empty
+                        let source_tags = self
+                            .media_info
+                            .lock()
+                            .unwrap()
+                            .as_ref()
+                            .filter(|_| self.settings.copy_metadata)
+                            .map(|m| m.tags.clone())
+                            .unwrap_or_default();
+                        let hint = |key: &str| source_tags.get(key).cloned().unwrap_or_default();
+                        let title_hint = self
+                            .input_path
+                            .as_ref()
+                            .and_then(|p| p.file_stem())
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let fields = [
+                            ("Title", &mut self.tags.title, source_tags.get("title").cloned().unwrap_or(title_hint)),
+                            ("Artist", &mut self.tags.artist, hint("artist")),
+                            ("Album", &mut self.tags.album, hint("album")),
+                            ("Year", &mut self.tags.year, hint("date")),
+                            ("Genre", &mut self.tags.genre, hint("genre")),
+                        ];
+                        for (label, value, hint) in fields {
+                            ui.label(tr(label));
+                            ui.add(egui::TextEdit::singleline(value).hint_text(hint).desired_width(150.0));
+                            ui.end_row();
+                        }
+
+                        ui.label(tr("Pattern"));
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.settings.filename_pattern)
+                                    .hint_text("%artist% - %title%")
+                                    .desired_width(110.0),
+                            )
+                            .on_hover_text(tr(
+                                "Fill tags from the filename when a file is selected.\n\
+                                 Fields: %title% %artist% %album% %year% %genre%",
+                            ));
+                            if ui.button(tr("Apply")).clicked() {
+                                self.apply_filename_pattern();
+                            }
+                        });
+                        ui.end_row();
+                    });
+
+                ui.checkbox(&mut self.settings.copy_metadata, tr("Keep source metadata"))
+                    .on_hover_text(tr("Copy title, artist, date, etc. from the video; fields above override them"));
+            });
+    }
+
+    /// Everything FFmpeg printed, for diagnosing failed conversions
+    fn log_ui(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(tr_args("FFmpeg log ({count} lines)", &[("count", &log::len())]))
+            .id_salt("ffmpeg_log")
+            .show(ui, |ui| {
+                let mut text = log::text();
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut text)
+                                .font(egui::TextStyle::Monospace)
+                                .interactive(false)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button(tr("📋 Copy")).clicked() {
+                        ui.ctx().copy_text(text.clone());
+                    }
+                    if ui.button(tr("💾 Save")).clicked()
+                        && let Some(path) = FileDialog::new().set_file_name("ffmpeg.log").save_file()
+                        && let Err(e) = fs::write(&path, &text)
+                    {
+                        println!("Failed to save log '{}': {}", path.display(), e);
+                    }
+                    if ui.button(tr("Clear")).clicked() {
+                        log::clear();
+                    }
+                });
+            });
+    }
+
+    /// Grid row picking the folder outputs go to
+    fn output_folder_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(tr("Output folder"));
+        ui.horizontal(|ui| {
+            let folder = match self.settings.output_dir {
+                Some(ref dir) => dir.file_name().unwrap_or(dir.as_os_str()).to_string_lossy().to_string(),
+                None => tr("Same as source"),
+            };
+            let mut changed = false;
+            if ui
+                .button(format!("📁 {}", folder))
+                .on_hover_text(match self.settings.output_dir {
+                    Some(ref dir) => dir.display().to_string(),
+                    None => tr("Pick a folder for converted files, e.g. when the source is on a read-only share or disc"),
+                })
+                .clicked()
+                && let Some(dir) = FileDialog::new().pick_folder()
+            {
+                self.settings.output_dir = Some(dir);
+                changed = true;
+            }
+            if self.settings.output_dir.is_some()
+                && icon_hint(ui.small_button("✖"), &tr("Save next to the source again")).clicked()
+            {
+                self.settings.output_dir = None;
+                changed = true;
+            }
+            if changed && let Some(input) = self.input_path.clone() {
+                self.set_output(Some(self.default_output(&input)));
+            }
+        });
+        ui.end_row();
+    }
+
+    /// Grid rows for the FFmpeg and ffprobe programs
+    fn program_paths_ui(&mut self, ui: &mut egui::Ui) {
+        for (label, path, hint) in [
+            ("FFmpeg", &mut self.settings.ffmpeg_path, "the one next to the app or on PATH"),
+            ("ffprobe", &mut self.settings.ffprobe_path, "next to FFmpeg or on PATH"),
+        ] {
+            ui.label(label);
+            ui.horizontal(|ui| {
+                let hint = tr(hint);
+                ui.add(egui::TextEdit::singleline(path).hint_text(&hint).desired_width(150.0))
+                    .on_hover_text(tr_args("Program used for {program}, leave empty to use {default}", &[("program", &label), ("default", &hint)]));
+                if icon_hint(ui.small_button("…"), &tr_args("Pick the {program} program", &[("program", &label)])).clicked()
+                    && let Some(picked) = FileDialog::new().pick_file()
+                {
+                    *path = picked.to_string_lossy().to_string();
+                }
+            });
+            ui.end_row();
+        }
+    }
+
+    /// CBR bitrate picker, with Auto matching the source
+    fn bitrate_ui(&mut self, ui: &mut egui::Ui, id_salt: &str) {
+        let auto_text = match self.auto_bitrate() {
+            Some(bitrate) => tr_args("Auto ({bitrate})", &[("bitrate", &bitrate)]),
+            None => tr("Auto"),
+        };
+        let selected_text = if self.settings.auto_bitrate {
+            auto_text.clone()
+        } else {
+            format!("{} kbps", self.settings.bitrate)
+        };
+        egui::ComboBox::from_id_salt(id_salt)
+            .width(100.0)
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.settings.auto_bitrate, true, auto_text)
+                    .on_hover_text(tr("Pick a bitrate that matches the source audio"));
+                for bitrate in BITRATES {
+                    let selected = !self.settings.auto_bitrate && self.settings.bitrate == bitrate;
+                    if ui.selectable_label(selected, format!("{} kbps", bitrate)).clicked() {
+                        self.settings.bitrate = bitrate;
+                        self.settings.auto_bitrate = false;
+                    }
+                }
+            });
+    }
+
+    /// Presets picked for batch items by their file type
+    fn extension_presets_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(tr("Presets by file type")).show(ui, |ui| {
+            let presets = self.settings.presets();
+            let mut removed = None;
+            egui::Grid::new("extension_presets").num_columns(3).spacing([10.0, 4.0]).show(ui, |ui| {
+                for (extension, name) in self.settings.extension_presets.iter_mut() {
+                    ui.label(format!(".{}", extension));
+                    egui::ComboBox::from_id_salt(("extension_preset", extension.as_str()))
+                        .width(120.0)
+                        .selected_text(name.as_str())
+                        .show_ui(ui, |ui| {
+                            for preset in &presets {
+                                ui.selectable_value(name, preset.name.clone(), &preset.name);
+                            }
+                        });
+                    if icon_hint(ui.small_button("✖"), &tr("Use the batch settings again")).clicked() {
+                        removed = Some(extension.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(extension) = removed {
+                self.settings.extension_presets.remove(&extension);
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.new_extension).hint_text("mkv").desired_width(60.0));
+                let extension = self.new_extension.trim().trim_start_matches('.').to_lowercase();
+                if ui
+                    .add_enabled(!extension.is_empty(), egui::Button::new(tr("Add")))
+                    .on_hover_text(tr("Queued files of this type get the chosen preset instead of the batch settings; per-file overrides still apply"))
+                    .clicked()
+                {
+                    let name = presets.iter().find(|p| p.matches(&self.settings)).unwrap_or(&presets[0]).name.clone();
+                    self.settings.extension_presets.insert(extension, name);
+                    self.new_extension.clear();
+                }
+            });
+        });
+    }
+
+    /// Replace the settings with an exported file, keeping what only makes sense on this machine
+    fn import_settings(&mut self) {
+        let Some(path) = FileDialog::new().add_filter(tr("Settings"), &["json"]).pick_file() else {
+            return;
+        };
+        match Settings::import(&path) {
+            Ok(imported) => {
+                self.settings = Settings {
+                    ffmpeg_path: self.settings.ffmpeg_path.clone(),
+                    ffprobe_path: self.settings.ffprobe_path.clone(),
+                    output_dir: self.settings.output_dir.clone(),
+                    window_size: self.settings.window_size,
+                    window_position: self.settings.window_position,
+                    ..imported
+                };
+            }
+            Err(e) => println!("Failed to import settings '{}': {}", path.display(), e),
+        }
+    }
+
+    /// Window gathering the settings that apply to every conversion, saved like the rest as they change
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        egui::Window::new(tr("Settings"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("settings_window")
+                    .num_columns(2)
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label(tr("Format"));
+                        egui::ComboBox::from_id_salt("settings_format")
+                            .width(100.0)
+                            .selected_text(self.settings.format.extension().to_uppercase())
+                            .show_ui(ui, |ui| {
+                                for format in [OutputFormat::Mp3, OutputFormat::M4b, OutputFormat::Flac] {
+                                    ui.add_enabled_ui(self.has_encoder(format), |ui| {
+                                        ui.selectable_value(&mut self.settings.format, format, format.extension().to_uppercase())
+                                            .on_disabled_hover_text(tr_args(
+                                                "Your FFmpeg build has no {encoder} encoder",
+                                                &[("encoder", &format.encoder())],
+                                            ));
+                                    });
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label(tr("Bitrate"));
+                        self.bitrate_ui(ui, "settings_bitrate");
+                        ui.end_row();
+
+                        self.output_folder_ui(ui);
+
+                        ui.label(tr("Batch"));
+                        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings.parallel_jobs)
+                                .range(1..=cores)
+                                .suffix(tr(" at a time")),
+                        )
+                        .on_hover_text(tr("Files converted at the same time"));
+                        ui.end_row();
+
+                        self.program_paths_ui(ui);
+
+                        ui.label(tr("Language"));
+                        let languages = i18n::languages();
+                        let current = languages
+                            .iter()
+                            .find(|l| l.code == self.settings.language)
+                            .map_or_else(|| self.settings.language.clone(), |l| l.name.clone());
+                        egui::ComboBox::from_id_salt("language")
+                            .selected_text(current)
+                            .show_ui(ui, |ui| {
+                                for language in languages {
+                                    ui.selectable_value(&mut self.settings.language, language.code, language.name);
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr("Add more by putting a .po catalog into the locales folder next to the settings"));
+                        ui.end_row();
+
+                        ui.label(tr("Theme"));
+                        ui.horizontal(|ui| {
+                            for theme in Theme::ALL {
+                                ui.selectable_value(&mut self.settings.theme, theme, tr(theme.name()));
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label(tr("Updates"));
+                        ui.checkbox(&mut self.settings.check_updates, tr("Check on startup"))
+                            .on_hover_text(tr("Ask GitHub for a newer release when the app starts; a link shows up under About when there is one"));
+                        ui.end_row();
+
+                        ui.label(tr("Notifications"));
+                        ui.checkbox(&mut self.settings.notifications, tr("When in the background"))
+                            .on_hover_text(tr("Announce finished and failed conversions while the window is out of sight"));
+                        ui.end_row();
+                    });
+                self.extension_presets_ui(ui);
+                ui.label(
+                    egui::RichText::new(tr("More options are under Advanced options"))
+                        .size(11.0)
+                        .color(Color32::GRAY),
+                );
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(tr("Export…"))
+                        .on_hover_text(tr("Save all settings and presets to one file, to set up another machine the same way"))
+                        .clicked()
+                        && let Some(path) = FileDialog::new()
+                            .add_filter(tr("Settings"), &["json"])
+                            .set_file_name("vid2mp3-settings.json")
+                            .save_file()
+                        && let Err(e) = self.settings.export(&path)
+                    {
+                        println!("Failed to export settings '{}': {}", path.display(), e);
+                    }
+                    if ui
+                        .button(tr("Import…"))
+                        .on_hover_text(tr("Replace all settings and presets with an exported file; this machine's FFmpeg paths and output folder are kept"))
+                        .clicked()
+                    {
+                        self.import_settings();
+                    }
+                    if self.confirm_reset {
+                        if ui.button(tr("Really reset")).clicked() {
+                            self.settings = self.settings.reset();
+                            self.confirm_reset = false;
+                            if let Some(input) = self.input_path.clone() {
+                                self.set_output(Some(self.default_output(&input)));
+                            }
+                        }
+                        if icon_hint(ui.button("✖"), &tr("Cancel")).clicked() {
+                            self.confirm_reset = false;
+                        }
+                    } else if ui
+                        .button(tr("Reset…"))
+                        .on_hover_text(tr("Put every setting back to its default; your presets and profiles are kept"))
+                        .clicked()
+                    {
+                        self.confirm_reset = true;
+                    }
+                });
+            });
+        self.show_settings = open;
+    }
+
+    fn advanced_options_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(tr("Advanced options"))
+            .show(ui, |ui| {
+                egui::Grid::new("advanced_options")
+                    .num_columns(2)
+                    .spacing([10.0, 6.0])
+                    .show(ui, |ui| {
+                        let available: Vec<OutputFormat> =
+                            OutputFormat::ALL.into_iter().filter(|&format| self.has_encoder(format)).collect();
+                        ui.label(tr("Also save as"));
+                        ui.horizontal(|ui| {
+                            for format in [OutputFormat::Mp3, OutputFormat::M4b, OutputFormat::Flac] {
+                                if format == self.settings.format {
+                                    continue;
+                                }
+                                let mut checked = self.settings.extra_formats.contains(&format);
+                                if ui
+                                    .add_enabled(
+                                        available.contains(&format),
+                                        egui::Checkbox::new(&mut checked, format.extension().to_uppercase()),
+                                    )
+                                    .on_disabled_hover_text(tr_args("Your FFmpeg build has no {encoder} encoder", &[("encoder", &format.encoder())]))
+                                    .changed()
+                                {
+                                    self.settings.extra_formats.retain(|&f| f != format);
+                                    if checked {
+                                        self.settings.extra_formats.push(format);
+                                    }
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("Write these formats too, sharing the analysis passes"));
+                        ui.end_row();
+
+                        ui.label(tr("If the file exists"));
+                        egui::ComboBox::from_id_salt("existing_output")
+                            .width(120.0)
+                            .selected_text(tr(self.settings.existing_output.name()))
+                            .show_ui(ui, |ui| {
+                                for option in ExistingOutput::ALL {
+                                    ui.selectable_value(&mut self.settings.existing_output, option, tr(option.name()));
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr("Used for single files and batches; batches can't stop to ask, so there Ask numbers the new file"));
+                        ui.end_row();
+
+                        ui.label(tr("After converting"));
+                        egui::ComboBox::from_id_salt("source_action")
+                            .width(120.0)
+                            .selected_text(tr(self.settings.source_action.name()))
+                            .show_ui(ui, |ui| {
+                                for option in SourceAction::ALL {
+                                    ui.selectable_value(&mut self.settings.source_action, option, tr(option.name()));
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr("What happens to the source file; it's only moved once the output is there and has the right length"));
+                        ui.end_row();
+
+                        ui.label(tr("Timestamps"));
+                        ui.checkbox(&mut self.settings.preserve_timestamps, tr("Copy from source"))
+                            .on_hover_text(tr("Give the output the source's modified and created dates, so converted archives sort the same way. Linux keeps the created date of the new file"));
+                        ui.end_row();
+
+                        ui.label(tr("Sample rate"));
+                        let rate_text = |rate: Option<u32>| match rate {
+                            Some(rate) => format!("{:.2} kHz", rate as f32 / 1000.0),
+                            None => tr("Keep original"),
+                        };
+                        egui::ComboBox::from_id_salt("sample_rate")
+                            .width(120.0)
+                            .selected_text(rate_text(self.settings.sample_rate))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.settings.sample_rate, None, rate_text(None));
+                                for rate in SAMPLE_RATES {
+                                    ui.selectable_value(&mut self.settings.sample_rate, Some(rate), rate_text(Some(rate)));
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label(tr("Channels"));
+                        let channels_text = |channels: Channels| {
+                            tr(match channels {
+                                Channels::Keep => "Keep original",
+                                Channels::Stereo => "Stereo",
+                                Channels::Mono => "Mono",
+                            })
+                        };
+                        egui::ComboBox::from_id_salt("channels")
+                            .width(120.0)
+                            .selected_text(channels_text(self.settings.channels))
+                            .show_ui(ui, |ui| {
+                                for channels in [Channels::Keep, Channels::Stereo, Channels::Mono] {
+                                    ui.selectable_value(&mut self.settings.channels, channels, channels_text(channels));
+                                }
+                            });
+                        ui.end_row();
+
+                        // Only worth a picker when there is more than one audio stream
+                        let audio_tracks: Vec<String> = self
+                            .media_info
+                            .lock()
+                            .unwrap()
+                            .as_ref()
+                            .map(|m| m.audio_streams().map(|s| s.label()).collect())
+                            .unwrap_or_default();
+                        if audio_tracks.len() > 1 {
+                            ui.label(tr("Audio track"));
+                            let track_text = |track: Option<usize>| match track {
+                                Some(n) => format!("#{} {}", n + 1, audio_tracks[n]),
+                                None => tr("Default"),
+                            };
+                            ui.vertical(|ui| {
+                                ui.add_enabled_ui(!self.all_tracks, |ui| {
+                                    egui::ComboBox::from_id_salt("audio_track")
+                                        .width(150.0)
+                                        .selected_text(track_text(self.audio_track))
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(&mut self.audio_track, None, track_text(None));
+                                            for n in 0..audio_tracks.len() {
+                                                ui.selectable_value(&mut self.audio_track, Some(n), track_text(Some(n)));
+                                            }
+                                        });
+                                });
+                                ui.checkbox(&mut self.all_tracks, tr("All tracks, one file each"))
+                                    .on_hover_text(tr("Adds the language code to each filename, e.g. name.eng.mp3"));
+                            });
+                            ui.end_row();
+                        }
+
+                        let surround = self
+                            .media_info
+                            .lock()
+                            .unwrap()
+                            .as_ref()
+                            .is_some_and(|m| m.is_surround(self.audio_track));
+                        if surround {
+                            ui.label(tr("Downmix"));
+                            let downmix_text = |downmix: Downmix| {
+                                tr(match downmix {
+                                    Downmix::Default => "Default",
+                                    Downmix::ProLogic2 => "Dolby Pro Logic II",
+                                    Downmix::DialogueBoost => "Dialogue boost",
+                                })
+                            };
+                            egui::ComboBox::from_id_salt("downmix")
+                                .width(150.0)
+                                .selected_text(downmix_text(self.settings.downmix))
+                                .show_ui(ui, |ui| {
+                                    for downmix in [Downmix::Default, Downmix::ProLogic2, Downmix::DialogueBoost] {
+                                        ui.selectable_value(&mut self.settings.downmix, downmix, downmix_text(downmix));
+                                    }
+                                })
+                                .response
+                                .on_hover_text(tr("How surround sound is mixed down to stereo"));
+                            ui.end_row();
+                        }
+
+                        ui.label(tr("Ringtone"));
+                        let duration = self.media_info.lock().unwrap().as_ref().and_then(|m| m.duration);
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.ringtone, "")
+                                .on_hover_text(tr("Export a 30 second phone-friendly clip"));
+                            let format_text = |format: OutputFormat| {
+                                tr(match format {
+                                    OutputFormat::M4r => "iPhone (M4R)",
+                                    _ => "Android (MP3)",
+                                })
+                            };
+                            ui.add_enabled_ui(self.ringtone, |ui| {
+                                egui::ComboBox::from_id_salt("ringtone_format")
+                                    .width(110.0)
+                                    .selected_text(format_text(self.settings.ringtone_format))
+                                    .show_ui(ui, |ui| {
+                                        for format in [OutputFormat::Mp3, OutputFormat::M4r] {
+                                            ui.add_enabled_ui(available.contains(&format), |ui| {
+                                                ui.selectable_value(&mut self.settings.ringtone_format, format, format_text(format));
+                                            });
+                                        }
+                                    });
+                            });
+                        });
+                        ui.end_row();
+
+                        if self.settings.format == OutputFormat::M4b {
+                            ui.label(tr("Chapters"));
+                            let source_chapters = self
+                                .media_info
+                                .lock()
+                                .unwrap()
+                                .as_ref()
+                                .map(|m| m.chapters.clone())
+                                .unwrap_or_default();
+                            if source_chapters.is_empty() {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.settings.chapter_minutes)
+                                        .range(0..=120)
+                                        .prefix(tr("every "))
+                                        .suffix(tr(" min")),
+                                )
+                                .on_hover_text(tr("Chapter length for sources without chapters (0 = none)"));
+                            } else {
+                                let list = source_chapters
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, c)| {
+                                        let title = c.title.clone().unwrap_or_else(|| tr_args("Chapter {number}", &[("number", &(i + 1))]));
+                                        format!("{}  {}", timecode::format(c.start), title)
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                ui.label(tr_args("{count} from source", &[("count", &source_chapters.len())]))
+                                    .on_hover_text(list);
+                            }
+                            ui.end_row();
+                        }
+
+                        if self.ringtone {
+                            ui.label(tr("Clip start"));
+                            let max_start = duration.map_or(0.0, |d| (d - RINGTONE_LENGTH).max(0.0));
+                            ui.add(
+                                egui::Slider::new(&mut self.ringtone_start, 0.0..=max_start)
+                                    .custom_formatter(|v, _| {
+                                        format!("{} – {}", timecode::format(v), timecode::format(v + RINGTONE_LENGTH))
+                                    }),
+                            );
+                            ui.end_row();
+                        }
+
+                        ui.label(tr("Trim"));
+                        ui.add_enabled_ui(!self.ringtone, |ui| ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.trim_start_text)
+                                    .hint_text("00:00")
+                                    .desired_width(55.0),
+                            )
+                            .on_hover_text(tr("Start time (hh:mm:ss, mm:ss or seconds)"));
+                            ui.label("–");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.trim_end_text)
+                                    .hint_text(duration.map(timecode::format).unwrap_or_else(|| tr("end")))
+                                    .desired_width(55.0),
+                            )
+                            .on_hover_text(tr("End time (hh:mm:ss, mm:ss or seconds)"));
+                        }));
+                        ui.end_row();
+
+                        ui.label(tr("Audio offset"));
+                        ui.add(
+                            egui::DragValue::new(&mut self.audio_offset_ms)
+                                .range(-10000..=10000)
+                                .speed(5.0)
+                                .suffix(" ms"),
+                        )
+                        .on_hover_text(tr("Positive delays the audio, negative makes it start earlier"));
+                        ui.end_row();
+
+                        ui.label(tr("Fade in/out"));
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.settings.fade_in).range(0.0..=30.0).speed(0.1).suffix(" s"))
+                                .on_hover_text(tr("Fade-in duration (0 = off)"));
+                            ui.add(egui::DragValue::new(&mut self.settings.fade_out).range(0.0..=30.0).speed(0.1).suffix(" s"))
+                                .on_hover_text(tr("Fade-out duration (0 = off)"));
+                        });
+                        ui.end_row();
+
+                        ui.label(tr("Equalizer"));
+                        egui::ComboBox::from_id_salt("eq")
+                            .width(110.0)
+                            .selected_text(tr(self.eq.name()))
+                            .show_ui(ui, |ui| {
+                                for preset in EqPreset::ALL {
+                                    ui.selectable_value(&mut self.eq, preset, tr(preset.name()));
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label(tr("Speed"));
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.settings.speed).range(0.5..=3.0).speed(0.05).suffix("×"))
+                                .on_hover_text(tr("Playback speed (1× = unchanged)"));
+                            ui.checkbox(&mut self.settings.preserve_pitch, tr("Keep pitch"))
+                                .on_hover_text(tr("Change tempo without changing pitch"));
+                        });
+                        ui.end_row();
+
+                        ui.checkbox(&mut self.settings.normalize, tr("Normalize"))
+                            .on_hover_text(tr("Two-pass loudness normalization for consistent volume"));
+                        ui.add_enabled(
+                            self.settings.normalize,
+                            egui::Slider::new(&mut self.settings.loudness_target, -24.0..=-10.0)
+                                .step_by(1.0)
+                                .suffix(" LUFS"),
+                        )
+                        .on_hover_text(tr("-23 broadcast, -16 podcasts, -14 streaming"));
+                        ui.end_row();
+
+                        ui.label(tr("Split"));
+                        ui.horizontal(|ui| {
+                            let split_text = |split: SplitMode| {
+                                tr(match split {
+                                    SplitMode::None => "Off",
+                                    SplitMode::Silence => "At silence",
+                                    SplitMode::Fixed => "Every N min",
+                                })
+                            };
+                            egui::ComboBox::from_id_salt("split")
+                                .width(80.0)
+                                .selected_text(split_text(self.settings.split))
+                                .show_ui(ui, |ui| {
+                                    for split in [SplitMode::None, SplitMode::Silence, SplitMode::Fixed] {
+                                        ui.selectable_value(&mut self.settings.split, split, split_text(split));
+                                    }
+                                });
+                            if self.settings.split == SplitMode::Silence {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.settings.silence_min_duration)
+                                        .range(0.5..=30.0)
+                                        .speed(0.1)
+                                        .suffix(" s"),
+                                )
+                                .on_hover_text(tr("Minimum gap of silence between tracks"));
+                            }
+                            if self.settings.split == SplitMode::Fixed {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.settings.segment_minutes)
+                                        .range(1..=240)
+                                        .suffix(tr(" min")),
+                                )
+                                .on_hover_text(tr("Length of each part"));
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label(tr("Extra args"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings.extra_args)
+                                .hint_text("-compression_level 0")
+                                .desired_width(150.0),
+                        )
+                        .on_hover_text(tr("Additional FFmpeg output options, quoted like in a shell"));
+                        ui.end_row();
+
+                        ui.label(tr("Stall timeout"));
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings.stall_timeout)
+                                .range(0..=3600)
+                                .custom_formatter(|n, _| if n == 0.0 { tr("off") } else { format!("{} s", n) }),
+                        )
+                        .on_hover_text(tr("Stop FFmpeg when it makes no progress for this long, e.g. stuck on a damaged file"));
+                        ui.end_row();
+
+                        ui.label(tr("Priority"));
+                        ui.checkbox(&mut self.settings.low_priority, tr("Below normal"))
+                            .on_hover_text(tr("Run FFmpeg at below-normal priority so the app and the rest of the desktop stay responsive while it encodes"));
+                        ui.end_row();
+
+                        ui.label(tr("Decoding"));
+                        ui.checkbox(&mut self.settings.hardware_decode, tr("Hardware"))
+                            .on_hover_text(tr("Let the GPU decode the video (D3D11, VideoToolbox or VA-API), so 4K and HEVC sources don't load every CPU core. FFmpeg uses the CPU when no hardware decoder fits"));
+                        ui.end_row();
+
+                        #[cfg(feature = "native")]
+                        {
+                            ui.label(tr("Encoder"));
+                            ui.checkbox(&mut self.settings.native_backend, tr("Built-in FFmpeg"))
+                                .on_hover_text(tr("Convert with the FFmpeg libraries inside the app instead of running ffmpeg. Normalization, splitting and the other extra passes still need ffmpeg"));
+                            ui.end_row();
+                        }
+                    });
+
+                if let Err(e) = self.job_options() {
+                    ui.label(egui::RichText::new(e).size(11.0).color(Color32::from_rgb(248, 113, 113)));
+                }
+
+                ui.add_enabled(
+                    self.settings.split == SplitMode::None,
+                    egui::Checkbox::new(&mut self.settings.write_cue, tr("Write cue sheet")),
+                )
+                .on_hover_text(tr("Save a .cue file marking chapters, or gaps of silence, so players can skip between tracks"))
+                .on_disabled_hover_text(tr("Only used when the output isn't split"));
+
+                ui.checkbox(&mut self.settings.spectrogram, tr("Save spectrogram image"))
+                    .on_hover_text(tr("Render a PNG of the audio spectrum next to the output, to spot silent or clipped audio at a glance"));
+
+                ui.checkbox(&mut self.settings.replaygain, tr("Write ReplayGain tags"))
+                    .on_hover_text(tr("Analyze the result so players can match its volume to other tracks"));
+
+                ui.checkbox(&mut self.settings.embed_cover, tr("Embed thumbnail as album art"))
+                    .on_hover_text(tr("Store the video thumbnail as the MP3's cover image"));
+
+                let subtitle_count = self
+                    .media_info
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(0, |m| m.text_subtitles().len());
+                ui.add_enabled(
+                    subtitle_count > 0,
+                    egui::Checkbox::new(&mut self.settings.export_subtitles, tr("Export subtitles as .srt")),
+                )
+                .on_hover_text(tr_args("Save {count} subtitle track(s) next to the output", &[("count", &subtitle_count)]))
+                .on_disabled_hover_text(tr("The video has no text subtitles"));
+
+                let format = self.effective_settings().format;
+                let can_copy = self
+                    .media_info
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .is_some_and(|m| m.can_copy_to(format, self.audio_track));
+                ui.add_enabled(
+                    can_copy,
+                    egui::Checkbox::new(&mut self.settings.stream_copy, tr("Lossless extract")),
+                )
+                .on_hover_text(tr_args(
+                    "Copy the audio as-is when it is already {codec}, as {format} needs, and no filters, sample rate or channel change are enabled (bitrate and other options are ignored)",
+                    &[("codec", &format.codec().to_uppercase()), ("format", &format.extension().to_uppercase())],
+                ))
+                .on_disabled_hover_text(tr_args(
+                    "Only available when the source audio is already {codec}, the codec {format} holds",
+                    &[("codec", &format.codec().to_uppercase()), ("format", &format.extension().to_uppercase())],
+                ));
+
+                let can_preview = self.input_path.is_some() && self.job_options().is_ok();
+                if ui
+                    .add_enabled(can_preview, egui::Button::new(tr("Preview command")))
+                    .on_hover_text(tr("Show the FFmpeg commands without converting"))
+                    .clicked()
+                {
+                    self.preview_command();
+                }
+            });
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Handle dropped files: one file opens in the editor, several go to the queue
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if !dropped.is_empty() {
+            self.open_paths(dropped, false);
+            self.dropped_file = true;
+        }
+
+        // Ctrl+V with files or paths on the clipboard queues them, unless a text field takes the paste
+        let pasted = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
+            })
+        });
+        if let Some(text) = pasted
+            && !ctx.wants_keyboard_input()
+        {
+            self.paste_files(Some(&text));
+        }
+
+        // Keep item states fresh while the queue works in the background
+        let running = queue::is_running(&self.queue);
+        if running {
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+
+        // Finished work is announced with a notification when the window is in the background
+        let focused = ctx.input(|i| i.viewport().focused) == Some(true);
+
+        // A batch that ran out of files, rather than being stopped, triggers the finish action
+        if self.queue_was_running && !running {
+            let items = self.queue.lock().unwrap().clone();
+            if !items.iter().any(|i| matches!(i.state, ItemState::Waiting)) {
+                let output = items.iter().find_map(|item| match item.state {
+                    ItemState::Done(ref outputs) | ItemState::Suspicious(ref outputs, _) => outputs.first().cloned(),
+                    _ => None,
+                });
+                if !focused && self.settings.notifications {
+                    let failed = items.iter().filter(|i| matches!(i.state, ItemState::Failed(_))).count();
+                    let skipped = items.iter().filter(|i| matches!(i.state, ItemState::Skipped)).count();
+                    let mut body = tr_args("{count} files converted", &[("count", &(items.len() - failed - skipped))]);
+                    if failed > 0 {
+                        body += &tr_args(", {count} failed", &[("count", &failed)]);
+                    }
+                    if skipped > 0 {
+                        body += &tr_args(", {count} skipped", &[("count", &skipped)]);
+                    }
+                    notify::show(tr("Batch finished"), body, output.clone());
+                }
+                self.finish_action.run(output.as_deref());
+            }
+        }
+        self.queue_was_running = running;
+
+        let status = self.status.lock().unwrap().clone();
+        let converting = matches!(status, Status::Converting(_));
+        if self.was_converting && !converting && !focused && self.settings.notifications {
+            let name = self
+                .input_path
+                .as_ref()
+                .map(|p| p.file_name().unwrap_or_default().to_string_lossy().to_string())
+                .unwrap_or_default();
+            match status {
+                Status::Done(outputs, _) | Status::Suspicious(outputs, _) => {
+                    notify::show(tr_args("{name} converted", &[("name", &name)]), tr("Click to open the folder"), outputs.first().cloned());
+                }
+                Status::Error(_) => notify::show(tr_args("{name} failed", &[("name", &name)]), tr("See the app for details"), None),
+                _ => {}
+            }
+        }
+        self.was_converting = converting;
+
+        let taskbar_state = self.taskbar_state();
+        if let Some(ref mut taskbar) = self.taskbar {
+            taskbar.set(taskbar_state);
+        }
+        self.handle_tray(ctx);
+
+        // Start a scheduled batch once its time has come
+        if let Some(start) = self.scheduled_start {
+            if chrono::Local::now() >= start {
+                self.scheduled_start = None;
+                if !queue::is_running(&self.queue) {
+                    println!("Starting scheduled batch");
+                    self.convert_queue();
+                }
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_secs(1));
+            }
+        }
+
+        // Offer to pick up a batch the app was killed in the middle of
+        if let Some(ref saved) = self.interrupted {
+            let waiting = saved.items.iter().filter(|i| matches!(i.state, ItemState::Waiting)).count();
+            let mut choice = None;
+            egui::Window::new(tr("Unfinished batch"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(tr_args("{count} files were still waiting to be converted when the app closed.", &[("count", &waiting)]));
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("Resume")).clicked() {
+                            choice = Some(true);
+                        }
+                        if ui.button(tr("Discard")).clicked() {
+                            choice = Some(false);
+                        }
+                    });
+                });
+            match choice {
+                Some(true) => {
+                    let saved = self.interrupted.take().unwrap();
+                    self.queue.lock().unwrap().extend(saved.items);
+                    self.batch_output_dir = saved.output_dir;
+                    self.convert_queue();
+                }
+                Some(false) => {
+                    self.interrupted = None;
+                    queue::discard_saved();
+                }
+                None => {}
+            }
+        }
+
+        if let Some(ref existing) = self.overwrite_prompt {
+            let mut choice = None;
+            egui::Window::new(tr("File already exists"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    for path in existing {
+                        ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                    }
+                    ui.checkbox(&mut self.remember_overwrite, tr("Remember my choice"))
+                        .on_hover_text(tr("Can be changed again under Advanced options"));
+                    ui.horizontal(|ui| {
+                        for option in [ExistingOutput::Overwrite, ExistingOutput::Rename, ExistingOutput::Skip] {
+                            if ui.button(tr(option.name())).clicked() {
+                                choice = Some(option);
+                            }
+                        }
+                    });
+                });
+            if let Some(choice) = choice {
+                if self.remember_overwrite {
+                    self.settings.existing_output = choice;
+                }
+                self.overwrite_prompt = None;
+                self.resolve_existing(choice);
+            }
+        }
+
+        self.ffmpeg_download_ui(ctx);
+
+        // Show info popup window
+        if self.show_info_popup {
+            let update = self.update.lock().unwrap().clone();
+            let update_state = self.update_download.lock().unwrap().clone();
+            egui::Window::new(tr("About"))
+                .collapsible(false)
+                .resizable(false)
+                .fixed_size(Vec2::new(250.0, if update.is_some() { 230.0 } else { 180.0 }))
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(10.0);
+                        ui.label(
+                            egui::RichText::new(tr_args("Version {version}", &[("version", &env!("CARGO_PKG_VERSION"))]))
+                                .size(12.0)
+                                .color(secondary_text(ui)),
+                        );
+                        if let Some(ref release) = update {
+                            ui.add_space(5.0);
+                            ui.hyperlink_to(tr_args("v{version} available", &[("version", &release.version)]), &release.page)
+                                .on_hover_text(tr("Open the release page"));
+                            match update_state {
+                                DownloadState::Downloading(progress) => {
+                                    ui.add(egui::ProgressBar::new(progress).desired_width(200.0).show_percentage());
+                                    ctx.request_repaint_after(std::time::Duration::from_millis(250));
+                                }
+                                DownloadState::Done => {
+                                    ui.label(egui::RichText::new(tr("Saved to your downloads folder")).size(11.0).color(Color32::GRAY));
+                                }
+                                _ => {
+                                    if let DownloadState::Failed(ref e) = update_state {
+                                        ui.label(egui::RichText::new(e).size(11.0).color(Color32::from_rgb(248, 113, 113)));
+                                    }
+                                    if let Some(ref asset) = release.asset
+                                        && ui.small_button(tr("Download")).on_hover_text(&asset.name).clicked()
+                                    {
+                                        update::download(asset.clone(), Arc::clone(&self.update_download));
+                                    }
+                                }
+                            }
+                        }
+                        ui.add_space(5.0);
+                        ui.label(
+                            egui::RichText::new(tr("Powered by FFmpeg"))
+                                .size(12.0)
+                                .color(secondary_text(ui)),
+                        );
+                        ui.add_space(15.0);
+                        if ui.button(tr("Close")).clicked() {
+                            self.show_info_popup = false;
+                        }
+                        ui.add_space(10.0);
+                    });
+                });
+        }
+
+        if self.show_settings {
+            self.settings_window(ctx);
+        }
+
+        // Show command preview window
+        if let Some(mut preview) = self.command_preview.clone() {
+            let mut open = true;
+            egui::Window::new(tr("FFmpeg command"))
+                .open(&mut open)
+                .collapsible(false)
+                .default_width(280.0)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().max_height(220.0).show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut preview)
+                                .font(egui::TextStyle::Monospace)
+                                .interactive(false)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+                    ui.label(
+                        egui::RichText::new(tr("Measured loudness and split points are filled in when converting"))
+                            .size(11.0)
+                            .color(Color32::GRAY),
+                    );
+                    if ui.button(tr("📋 Copy")).clicked() {
+                        ctx.copy_text(preview.clone());
+                    }
+                });
+            if !open {
+                self.command_preview = None;
+            }
+        }
+
+        egui::CentralPanel::default()
+            .show(ctx, |ui| {
+                // Info button in the top-right corner
+                if let Some(ref icon) = self.info_icon {
+                    let info_rect = egui::Rect::from_min_size(
+                        egui::pos2(ui.max_rect().right() - 18.0, ui.max_rect().top()),
+                        Vec2::splat(16.0),
+                    );
+                    let info_btn = ui.put(
+                        info_rect,
+                        egui::Button::image(egui::Image::new(icon).fit_to_exact_size(Vec2::splat(16.0))).frame(false),
+                    );
+                    if info_btn.hovered() {
+                        ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
+                    }
+                    let about = tr(if self.update.lock().unwrap().is_some() { "About · update available" } else { "About" });
+                    if info_btn.on_hover_text(about).clicked() {
+                        self.show_info_popup = true;
+                    }
+                }
+
+                // Settings button left of it
+                let settings_rect = egui::Rect::from_min_size(
+                    egui::pos2(ui.max_rect().right() - 42.0, ui.max_rect().top() - 2.0),
+                    Vec2::splat(20.0),
+                );
+                let settings_btn = ui.put(
+                    settings_rect,
+                    egui::Button::new(egui::RichText::new("⚙").size(15.0)).frame(false),
+                );
+                if settings_btn.hovered() {
+                    ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
+                }
+                if icon_hint(settings_btn, &tr("Settings")).clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+
+                // Profile picker in the opposite corner
+                let profile_rect = egui::Rect::from_min_size(ui.max_rect().min, Vec2::new(200.0, 20.0));
+                ui.scope_builder(egui::UiBuilder::new().max_rect(profile_rect), |ui| self.profile_ui(ui));
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+
                         ui.add_space(20.0);
 
-                        // Convert button
-                        let can_convert = self.input_path.is_some()
-                            && !matches!(*self.status.lock().unwrap(), Status::Converting);
+                        // Drop zone
+                        let width = content_width(ui);
+                        let drop_zone_size = Vec2::new(width, (width * 0.64).min(280.0));
+                        let (rect, response) = ui.allocate_exact_size(drop_zone_size, egui::Sense::click());
+                        let drop_zone_name = match self.input_path {
+                            Some(ref path) => tr_args(
+                                "{name} selected, choose another video or audio file",
+                                &[("name", &path.file_name().unwrap_or_default().to_string_lossy())],
+                            ),
+                            None => tr("Choose a video or audio file to convert, or drop one here"),
+                        };
+                        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, true, &drop_zone_name));
+
+                        // Draw dashed border
+                        let painter = ui.painter();
+                        let stroke = Stroke::new(2.0, Color32::GRAY);
+                        let rounding = CornerRadius::same(12);
+
+                        painter.rect_stroke(rect, rounding, stroke, egui::StrokeKind::Outside);
+
+                        // Load and display thumbnail if video is selected
+                        if self.input_path.is_some() {
+                            // Check if thumbnail is ready to load
+                            if self.video_thumbnail.is_none() {
+                                let thumb_path_opt = self.thumbnail_path.lock().unwrap().clone();
+                                if let Some(thumb_path) = thumb_path_opt {
+                                    println!("Loading thumbnail from: {:?}", thumb_path);
+                                    self.video_thumbnail = self.load_icon_from_file(ctx, &thumb_path);
+                                    if self.video_thumbnail.is_some() {
+                                        println!("Thumbnail loaded successfully!");
+                                    } else {
+                                        println!("Failed to load thumbnail image");
+                                    }
+                                    self.thumbnail_loading = false;
+                                }
+                            }
+
+                            // Display thumbnail if available
+                            if let Some(ref thumbnail) = self.video_thumbnail {
+                                // Draw thumbnail inside the drop zone with rounded corners
+                                let thumb_rect = rect.shrink(4.0); // Shrink slightly to fit within border
+
+                                // Calculate rounded corners vertices and UVs
+                                let uv_rect = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+
+                                // Simple approach: draw image then mask with rounded rect
+                                // Draw the image first
+                                painter.image(
+                                    thumbnail.id(),
+                                    thumb_rect,
+                                    uv_rect,
+                                    Color32::WHITE,
+                                );
+
+                                // Draw a rounded rect frame to create the rounded corner effect
+                                // by covering the corners with the background color
+                                let bg_color = ui.visuals().window_fill();
+                                painter.rect_stroke(thumb_rect, rounding, Stroke::new(4.0, bg_color), egui::StrokeKind::Outside);
+
+                                // Optionally: Draw a subtle overlay on hover
+                                if response.hovered() {
+                                    painter.rect_filled(thumb_rect, rounding, Color32::from_black_alpha(20));
+                                }
+                            } else {
+                                // Draw play icon when thumbnail is loading or failed
+                                let center = rect.center();
+                                let icon_size = 40.0;
+
+                                // Triangle play button
+                                let points = vec![
+                                    egui::pos2(center.x - icon_size * 0.4, center.y - icon_size * 0.5),
+                                    egui::pos2(center.x - icon_size * 0.4, center.y + icon_size * 0.5),
+                                    egui::pos2(center.x + icon_size * 0.5, center.y),
+                                ];
+                                painter.add(egui::Shape::convex_polygon(
+                                    points,
+                                    Color32::GRAY,
+                                    Stroke::NONE,
+                                ));
 
-                        let btn_color = if can_convert {
-                            Color32::from_rgb(34, 197, 94)
+                                // Request repaint if still loading
+                                if self.thumbnail_loading {
+                                    ctx.request_repaint();
+                                }
+                            }
                         } else {
-                            Color32::from_rgb(150, 200, 150)
-                        };
+                            // Draw play icon when no video selected
+                            let center = rect.center();
+                            let icon_size = 40.0;
 
-                        let btn = ui.add_sized(
-                            [250.0, 35.0],
-                            egui::Button::new(
-                                egui::RichText::new("Convert to MP3")
-                                    .size(16.0)
-                                    .color(Color32::WHITE),
-                            )
-                            .fill(btn_color)
-                            .corner_radius(CornerRadius::same(25))
-                        )
-                        .on_hover_text("Start converting the selected video to MP3");
+                            // Triangle play button
+                            let points = vec![
+                                egui::pos2(center.x - icon_size * 0.4, center.y - icon_size * 0.5),
+                                egui::pos2(center.x - icon_size * 0.4, center.y + icon_size * 0.5),
+                                egui::pos2(center.x + icon_size * 0.5, center.y),
+                            ];
+                            painter.add(egui::Shape::convex_polygon(
+                                points,
+                                Color32::GRAY,
+                                Stroke::NONE,
+                            ));
+                        }
 
-                        if btn.hovered() {
+                        // Change cursor to pointer hand on hover
+                        if response.hovered() {
                             ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
                         }
 
-                        if btn.clicked() && can_convert {
-                            self.convert();
+                        // Whole folders go straight to the queue
+                        if self.input_path.is_none()
+                            && ui
+                                .small_button(tr("📁 Add folder"))
+                                .on_hover_text(tr("Convert every video and audio file in a folder and its subfolders"))
+                                .clicked()
+                            && let Some(folder) = FileDialog::new().pick_folder()
+                        {
+                            self.enqueue(vec![folder]);
+                        }
+                        // Copied files can't always be dropped, e.g. over remote desktop
+                        if self.input_path.is_none()
+                            && ui
+                                .small_button(tr("📋 Paste files"))
+                                .on_hover_text(tr("Queue files copied in Explorer or Finder, or paths copied as text (Ctrl+V)"))
+                                .clicked()
+                        {
+                            self.paste_files(None);
+                        }
+                        if let Some(problem) = self.input_problem.lock().unwrap().as_ref() {
+                            ui.label(egui::RichText::new(problem).size(11.0).color(Color32::from_rgb(248, 113, 113)));
+                            announce(ctx, "input_problem", problem);
+                        }
+                        self.media_info_ui(ui);
+
+                        if response.clicked()
+                            && let Some(path) = FileDialog::new()
+                                .add_filter(tr("Video and audio"), &[&VIDEO_EXTENSIONS[..], &AUDIO_EXTENSIONS[..]].concat())
+                                .add_filter(tr("Video"), &VIDEO_EXTENSIONS)
+                                .add_filter(tr("Audio"), &AUDIO_EXTENSIONS)
+                                .pick_file()
+                        {
+                            self.set_input(path);
                         }
+
+                        // Status text
+                        let status = self.status.lock().unwrap().clone();
+                        let text = if let Some(ref path) = self.input_path {
+                            let name = path.file_name().unwrap().to_string_lossy().to_string();
+                            match self.estimated_size() {
+                                Some(size) => format!("{} · ~{}", name, format_size(size)),
+                                None => name,
+                            }
+                        } else {
+                            tr("Drop a video or audio file here to convert \n (\"mp4\", \"mkv\", \"mov\", \"m4a\", \"flac\", \"wav\", ...)")
+                        };
+
+
+                        let text_color = match &status {
+                            Status::Done(..) => Color32::from_rgb(74, 222, 128),
+                            Status::Suspicious(..) => Color32::from_rgb(251, 191, 36),
+                            Status::Error(_) => Color32::from_rgb(248, 113, 113),
+                            _ => secondary_text(ui),
+                        };
+
+                        let display_text = match &status {
+                            Status::Converting(progress) => match progress.speed {
+                                Some(speed) => tr_args(
+                                    "Converting... {percent}% · {speed}x",
+                                    &[("percent", &format!("{:.0}", progress.fraction * 100.0)), ("speed", &format!("{:.1}", speed))],
+                                ),
+                                None => tr_args("Converting... {percent}%", &[("percent", &format!("{:.0}", progress.fraction * 100.0))]),
+                            },
+                            Status::Suspicious(..) => tr("Done, but suspicious"),
+                            Status::Done(outputs, elapsed) => {
+                                let size: u64 = outputs.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+                                let mut text = tr_args("Done in {time}", &[("time", &timecode::format(elapsed.as_secs_f64()))]);
+                                if outputs.len() > 1 {
+                                    text += &tr_args(" · {count} files, {size}", &[("count", &outputs.len()), ("size", &format_size(size))]);
+                                } else {
+                                    text += &format!(" · {}", format_size(size));
+                                }
+                                // Compared with the source, which can be gone by now
+                                if let Some(source) = self.input_path.as_ref().and_then(|p| fs::metadata(p).ok())
+                                    && source.len() > 0
+                                {
+                                    text += &tr_args(" ({percent}% of source)", &[("percent", &format!("{:.0}", size as f64 / source.len() as f64 * 100.0))]);
+                                }
+                                text
+                            }
+                            Status::Error(err) => match diagnose::diagnose(err) {
+                                Some(diagnosis) => tr(diagnosis.message),
+                                None => tr("Error occurred"),
+                            },
+                            _ => text,
+                        };
+                        // Progress is read out in steps of ten percent, every change would drown out everything else
+                        let announcement = match &status {
+                            Status::Converting(progress) => tr_args(
+                                "Converting... {percent}%",
+                                &[("percent", &((progress.fraction * 10.0).floor() as u32 * 10))],
+                            ),
+                            _ => display_text.clone(),
+                        };
+                        announce(ctx, "status", &announcement);
+
                         ui.add_space(20.0);
+                        // Status text with optional link icon (centered)
+                        ui.vertical_centered(|ui| {
+                        if let Status::Done(ref outputs, _) | Status::Suspicious(ref outputs, _) = status {
+                            // When done, use horizontal for text + icon
+                            ui.horizontal(|ui| {
+                                ui.add_space((ui.available_width() - 260.0) / 2.0); // Approximate centering
+                                let label = ui.label(
+                                    egui::RichText::new(&display_text)
+                                        .size(11.0)
+                                        .color(text_color),
+                                );
+                                if let Status::Suspicious(_, ref warning) = status {
+                                    label.on_hover_text(warning);
+                                }
+
+                                if let Some(output_path) = outputs.first() {
+                                    ui.add_space(5.0);
+                                    let link_btn = ui.add(
+                                        egui::Button::new(egui::RichText::new("📂").size(14.0)).frame(false),
+                                    );
+
+                                    if link_btn.hovered() {
+                                        ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
+                                    }
+
+                                    if link_btn.clicked() {
+                                        finish::reveal(output_path);
+                                    }
+
+                                    icon_hint(link_btn, &tr("Open file location"));
+
+                                    let play_btn = ui.add(
+                                        egui::Button::new(egui::RichText::new("▶").size(14.0)).frame(false),
+                                    );
+
+                                    if play_btn.hovered() {
+                                        ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
+                                    }
+
+                                    if play_btn.clicked() {
+                                        finish::open(output_path);
+                                    }
+
+                                    icon_hint(play_btn, &tr("Play"));
+
+                                    let copy_btn = ui.add(
+                                        egui::Button::new(egui::RichText::new("📋").size(14.0)).frame(false),
+                                    );
+
+                                    if copy_btn.hovered() {
+                                        ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
+                                    }
+
+                                    // Split outputs are copied one path per line
+                                    if copy_btn.clicked() {
+                                        let paths: Vec<String> = outputs.iter().map(|p| p.display().to_string()).collect();
+                                        ctx.copy_text(paths.join("\n"));
+                                    }
+
+                                    icon_hint(copy_btn, &tr(if outputs.len() > 1 { "Copy paths" } else { "Copy path" }));
+                                }
+                            });
+
+                            // List every part when the output was split
+                            if outputs.len() > 1 {
+                                egui::CollapsingHeader::new(tr_args("{count} parts", &[("count", &outputs.len())]))
+                                    .id_salt("output_parts")
+                                    .show(ui, |ui| {
+                                        egui::ScrollArea::vertical().max_height(80.0).show(ui, |ui| {
+                                            for output in outputs {
+                                                ui.label(
+                                                    egui::RichText::new(output.file_name().unwrap_or_default().to_string_lossy())
+                                                        .size(11.0)
+                                                        .color(secondary_text(ui)),
+                                                );
+                                            }
+                                        });
+                                    });
+                            }
+                        } else if let Status::Converting(progress) = status {
+                            ui.add(
+                                egui::ProgressBar::new(progress.fraction)
+                                    .desired_width(content_width(ui))
+                                    .desired_height(16.0)
+                                    .text(egui::RichText::new(&display_text).size(11.0)),
+                            );
+                            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+                        } else {
+                            // Simple centered label when not done
+                            let label = ui.label(
+                                egui::RichText::new(&display_text)
+                                    .size(11.0)
+                                    .color(text_color),
+                            );
+                            if let Status::Error(ref err) = status {
+                                match diagnose::diagnose(err) {
+                                    Some(diagnosis) => {
+                                        ui.label(egui::RichText::new(diagnosis.fix).size(11.0).color(Color32::GRAY));
+                                    }
+                                    None => {
+                                        label.on_hover_text(tr("Expand the details below for the full FFmpeg output"));
+                                    }
+                                }
+                                egui::CollapsingHeader::new(tr("Details"))
+                                    .id_salt("error_details")
+                                    .show(ui, |ui| {
+                                        egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                                            ui.add(
+                                                egui::TextEdit::multiline(&mut err.as_str())
+                                                    .font(egui::TextStyle::Monospace)
+                                                    .desired_width(f32::INFINITY),
+                                            );
+                                        });
+                                        if ui.button(tr("📋 Copy details")).clicked() {
+                                            ctx.copy_text(err.clone());
+                                        }
+                                    });
+                                // The hung FFmpeg is already stopped, its partial output gets overwritten
+                                if err.starts_with(pipeline::STALLED)
+                                    && self.input_path.is_some()
+                                    && ui.button(tr("🔄 Retry")).clicked()
+                                {
+                                    self.convert();
+                                }
+                            }
+                        }
+    });
+
+                        self.queue_ui(ui);
+
+                        ui.add_space(10.0);
+
+                        self.preset_ui(ui);
+
+                        // Output format and encoding mode toggles
+                        ui.horizontal(|ui| {
+                            ui.add_space((ui.available_width() - 210.0) / 2.0);
+                            for (format, label, hint) in [
+                                (OutputFormat::Mp3, "MP3", "MP3 audio file"),
+                                (OutputFormat::M4b, "M4B", "Audiobook with chapters"),
+                                (OutputFormat::Flac, "FLAC", "Lossless audio file"),
+                            ] {
+                                let available = self.has_encoder(format);
+                                ui.add_enabled_ui(available, |ui| {
+                                    ui.selectable_value(&mut self.settings.format, format, label)
+                                        .on_hover_text(tr(hint))
+                                        .on_disabled_hover_text(tr_args(
+                                            "Your FFmpeg build has no {encoder} encoder",
+                                            &[("encoder", &format.encoder())],
+                                        ));
+                                });
+                            }
+                            ui.separator();
+                            let has_mp3 = self.settings.output_formats().contains(&OutputFormat::Mp3);
+                            ui.add_enabled_ui(has_mp3, |ui| {
+                                ui.selectable_value(&mut self.settings.mode, EncodeMode::Cbr, "CBR")
+                                    .on_hover_text(tr("Constant bitrate"));
+                                ui.selectable_value(&mut self.settings.mode, EncodeMode::Vbr, "VBR")
+                                    .on_hover_text(tr("Variable bitrate: smaller files at comparable quality"));
+                            });
+                        });
+
+                        // Bitrate selector (CBR) or quality slider (VBR, MP3 only)
+                        ui.horizontal(|ui| {
+                            ui.add_space((ui.available_width() - 160.0) / 2.0);
+                            let formats = self.settings.output_formats();
+                            let mode = if formats.iter().all(|&f| f == OutputFormat::Flac) {
+                                None
+                            } else if formats.contains(&OutputFormat::Mp3) {
+                                Some(self.settings.mode)
+                            } else {
+                                Some(EncodeMode::Cbr)
+                            };
+                            match mode {
+                                None => {
+                                    ui.label(egui::RichText::new(tr("Lossless, no bitrate needed")).size(12.0).color(secondary_text(ui)));
+                                }
+                                Some(EncodeMode::Cbr) => {
+                                    ui.label(egui::RichText::new(tr("Bitrate")).size(12.0).color(secondary_text(ui)));
+                                    self.bitrate_ui(ui, "bitrate");
+                                }
+                                Some(EncodeMode::Vbr) => {
+                                    ui.label(egui::RichText::new(tr("Quality")).size(12.0).color(secondary_text(ui)));
+                                    ui.add(egui::Slider::new(&mut self.settings.vbr_quality, 0..=9))
+                                        .on_hover_text(tr("0 = best quality, 9 = smallest file"));
+                                }
+                            }
+                        });
+
+                        // A higher bitrate than the source can't add quality back
+                        if !self.ringtone
+                            && let Some(suggested) = self.wasted_bitrate_fix()
+                        {
+                            ui.horizontal(|ui| {
+                                ui.add_space((ui.available_width() - 230.0) / 2.0);
+                                ui.label(
+                                    egui::RichText::new(tr("⚠ Higher than the source audio"))
+                                        .size(11.0)
+                                        .color(Color32::from_rgb(251, 191, 36)),
+                                );
+                                if ui
+                                    .small_button(tr_args("Use {bitrate} kbps", &[("bitrate", &suggested)]))
+                                    .on_hover_text(tr("Match the source bitrate instead of wasting space"))
+                                    .clicked()
+                                {
+                                    self.settings.mode = EncodeMode::Cbr;
+                                    self.settings.bitrate = suggested;
+                                    self.settings.auto_bitrate = false;
+                                }
+                            });
+                        }
+
+                        // Output name, the extension follows the chosen format
+                        if self.input_path.is_some() && self.queue.lock().unwrap().is_empty() {
+                            ui.add_space(6.0);
+                            ui.horizontal(|ui| {
+                                ui.add_space((ui.available_width() - 300.0) / 2.0);
+                                ui.label(tr("Save as"));
+                                let response = ui.add(egui::TextEdit::singleline(&mut self.output_name).desired_width(200.0));
+                                if response.changed()
+                                    && self.output_name_error().is_none()
+                                    && let Some(ref output) = self.output_path
+                                {
+                                    let mut name = std::ffi::OsString::from(self.output_name.trim());
+                                    name.push(".mp3");
+                                    self.output_path = Some(output.with_file_name(name));
+                                }
+                                ui.label(format!(".{}", self.effective_settings().format.extension()));
+                                if icon_hint(
+                                    ui.add_enabled(self.can_convert(), egui::Button::new("💾").small()),
+                                    &tr("Save as… pick the folder and name, then convert (or Shift+click Convert)"),
+                                )
+                                .clicked()
+                                {
+                                    self.save_as();
+                                }
+                            });
+                            if let Some(error) = self.output_name_error() {
+                                ui.label(egui::RichText::new(error).size(11.0).color(Color32::from_rgb(248, 113, 113)));
+                            }
+                        }
+
+                        ui.add_space(10.0);
+
+                        // Bottom bar
+                        ui.horizontal(|ui| {
+                            ui.add_space(20.0);
+
+                            // Convert button, for the queue when there is one
+                            let waiting = self
+                                .queue
+                                .lock()
+                                .unwrap()
+                                .iter()
+                                .filter(|i| matches!(i.state, ItemState::Waiting))
+                                .count();
+                            let batch = !self.queue.lock().unwrap().is_empty();
+                            let can_convert = if batch {
+                                waiting > 0 && self.batch_job_options().is_ok() && !queue::is_running(&self.queue)
+                            } else {
+                                self.can_convert()
+                            };
+
+                            // While the queue runs the button stops it once the current files are done
+                            let running = batch && queue::is_running(&self.queue);
+                            let stopping = running && self.queue_paused.load(Ordering::Relaxed);
+                            // A single conversion can be cancelled outright
+                            let converting = !batch && matches!(*self.status.lock().unwrap(), Status::Converting(_));
+
+                            let btn_color = if (running && !stopping) || converting {
+                                Color32::from_rgb(239, 68, 68)
+                            } else if can_convert {
+                                Color32::from_rgb(34, 197, 94)
+                            } else {
+                                Color32::from_rgb(150, 200, 150)
+                            };
+
+                            let format_name = self
+                                .effective_settings()
+                                .output_formats()
+                                .iter()
+                                .map(|f| f.extension().to_uppercase())
+                                .collect::<Vec<_>>()
+                                .join(" + ");
+                            let btn = ui.add_sized(
+                                [content_width(ui), 35.0],
+                                egui::Button::new(
+                                    egui::RichText::new(if converting {
+                                        tr("Cancel conversion")
+                                    } else if stopping {
+                                        tr("Stopping after current file…")
+                                    } else if running {
+                                        tr("Stop after current file")
+                                    } else if batch {
+                                        tr_args("Convert {count} files to {format}", &[("count", &waiting), ("format", &format_name)])
+                                    } else {
+                                        tr_args("Convert to {format}", &[("format", &format_name)])
+                                    })
+                                        .size(16.0)
+                                        .color(Color32::WHITE),
+                                )
+                                .fill(btn_color)
+                                .corner_radius(CornerRadius::same(25))
+                            )
+                            .on_hover_text(if converting {
+                                tr("Stop FFmpeg and delete the partly written output")
+                            } else if running {
+                                tr("Let the files being converted finish, then stop; the rest stay waiting")
+                            } else if let Some(problem) = self.input_problem.lock().unwrap().clone().filter(|_| !batch) {
+                                problem
+                            } else {
+                                tr_args("Start converting the selected video to {format}", &[("format", &format_name)])
+                            });
+
+                            if btn.hovered() {
+                                ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
+                            }
+
+                            if btn.clicked() && converting {
+                                self.cancel_conversion.store(true, Ordering::Relaxed);
+                            } else if btn.clicked() && running {
+                                self.queue_paused.store(true, Ordering::Relaxed);
+                            } else if btn.clicked() && can_convert {
+                                if batch {
+                                    self.convert_queue();
+                                } else if ui.input(|i| i.modifiers.shift) {
+                                    self.save_as();
+                                } else {
+                                    self.start_conversion();
+                                }
+                            }
+                            ui.add_space(20.0);
+                        });
+                        // ui.add_space(20.0);
+
+                        ui.add_space(10.0);
+                        self.tags_ui(ui);
+                        self.advanced_options_ui(ui);
+                        self.log_ui(ui);
                     });
-                    // ui.add_space(20.0);
                 });
             });
+
+        // The window's place is stored as it closes, moving it around doesn't rewrite the settings
+        let (close, inner, outer) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.close_requested(), viewport.inner_rect, viewport.outer_rect)
+        });
+        if close {
+            if let Some(inner) = inner {
+                self.settings.window_size = Some([inner.width(), inner.height()]);
+            }
+            // Wayland doesn't tell apps where their windows are
+            if let Some(outer) = outer {
+                self.settings.window_position = Some([outer.min.x, outer.min.y]);
+            }
+        }
+
+        // Persist settings whenever they change
+        if self.settings != self.saved_settings {
+            if (&self.settings.ffmpeg_path, &self.settings.ffprobe_path)
+                != (&self.saved_settings.ffmpeg_path, &self.saved_settings.ffprobe_path)
+            {
+                ffmpeg::set_paths(&self.settings.ffmpeg_path, &self.settings.ffprobe_path);
+                self.check_ffmpeg();
+            }
+            if self.settings.language != self.saved_settings.language {
+                i18n::set_language(&self.settings.language);
+            }
+            if self.settings.theme != self.saved_settings.theme {
+                ctx.set_theme(self.settings.theme.preference());
+            }
+            self.settings.save();
+            self.saved_settings = self.settings.clone();
+        }
     }
 }
\ No newline at end of file
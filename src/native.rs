@@ -0,0 +1,322 @@
+use crate::converter::{ConversionFuture, Converter};
+use crate::ffmpeg::{self, JobOptions};
+use crate::pipeline::{self, Conversion, ProgressFn};
+use crate::probe::MediaInfo;
+use crate::progress::Progress;
+use crate::settings::{Channels, EncodeMode, OutputFormat, Settings, SplitMode};
+use ffmpeg_next::{self as av, Rescale, codec, filter, format, frame, media};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// FFmpeg scales LAME's VBR quality by this in `global_quality`
+const QP2LAMBDA: usize = 118;
+
+/// Converts with FFmpeg's libraries linked into the app, no ffmpeg program needed
+///
+/// Covers plain conversions; options that need extra passes or files still need the program.
+pub struct Native;
+
+impl Converter for Native {
+    fn convert<'a>(
+        &'a self,
+        input: &'a Path,
+        output: &'a Path,
+        settings: &'a Settings,
+        job: JobOptions,
+        media: Option<&'a MediaInfo>,
+        on_progress: &'a ProgressFn<'a>,
+    ) -> ConversionFuture<'a> {
+        Box::pin(convert(input, output, settings, job, media, on_progress))
+    }
+}
+
+/// Stops the blocking conversion when the future running it is dropped
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+async fn convert(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    job: JobOptions,
+    media: Option<&MediaInfo>,
+    on_progress: &ProgressFn<'_>,
+) -> Result<Conversion, String> {
+    if let Some(option) = unsupported(settings, &job) {
+        return Err(format!("{} needs the ffmpeg program, turn off the built-in encoder", option));
+    }
+
+    let mut filters = Vec::new();
+    if job.trim_start.is_some() || job.trim_end.is_some() {
+        let mut trim = format!("atrim=start={}", job.trim_start.unwrap_or(0.0));
+        if let Some(end) = job.trim_end {
+            trim.push_str(&format!(":end={}", end));
+        }
+        filters.push(format!("{},asetpts=PTS-STARTPTS", trim));
+    }
+    filters.extend(ffmpeg::audio_filters(settings, &job, media));
+    let filters = if filters.is_empty() { "anull".to_string() } else { filters.join(",") };
+    let source_duration = media.and_then(|m| m.duration);
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let _cancel_on_drop = CancelOnDrop(Arc::clone(&cancel));
+    let mut outputs = Vec::new();
+    let formats = settings.output_formats();
+    for (i, &format) in formats.iter().enumerate() {
+        let target = pipeline::output_path(input, output, format);
+        let (sender, mut progress) = tokio::sync::mpsc::unbounded_channel();
+        let part = pipeline::part_path(&target);
+        let task = {
+            let (input, part, settings, job, filters, cancel) =
+                (input.to_path_buf(), part.clone(), settings.clone(), job.clone(), filters.clone(), Arc::clone(&cancel));
+            tokio::task::spawn_blocking(move || {
+                let report = |fraction: f32| {
+                    let _ = sender.send(fraction);
+                };
+                transcode(&input, &part, format, &settings, &job, &filters, source_duration, &report, &cancel)
+            })
+        };
+        // Each format gets an equal share of the bar
+        while let Some(fraction) = progress.recv().await {
+            on_progress(Progress::at(fraction).step(i, formats.len()));
+        }
+        let result = task.await.map_err(|e| e.to_string()).and_then(|result| result);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&part);
+        }
+        result?;
+        std::fs::rename(&part, &target).map_err(|e| e.to_string())?;
+        outputs.push(target);
+    }
+    on_progress(Progress::at(1.0));
+    Ok(Conversion { outputs, warning: None })
+}
+
+/// First option in `settings` the built-in encoder can't carry out
+fn unsupported(settings: &Settings, job: &JobOptions) -> Option<&'static str> {
+    if settings.normalize {
+        Some("Loudness normalization")
+    } else if settings.split != SplitMode::None {
+        Some("Splitting")
+    } else if settings.write_cue {
+        Some("Writing a cue sheet")
+    } else if settings.spectrogram {
+        Some("Saving a spectrogram")
+    } else if settings.replaygain {
+        Some("ReplayGain tagging")
+    } else if settings.export_subtitles {
+        Some("Exporting subtitles")
+    } else if job.cover.is_some() {
+        Some("Embedding album art")
+    } else if job.chapters_file.is_some() {
+        Some("Writing chapters")
+    } else if !job.extra_args.is_empty() {
+        Some("Extra FFmpeg arguments")
+    } else {
+        None
+    }
+}
+
+/// Decode the audio of `input`, run it through `filters` and encode it to `output` as `format`
+#[allow(clippy::too_many_arguments)]
+fn transcode(
+    input: &Path,
+    output: &Path,
+    output_format: OutputFormat,
+    settings: &Settings,
+    job: &JobOptions,
+    filters: &str,
+    source_duration: Option<f64>,
+    on_progress: &dyn Fn(f32),
+    cancel: &AtomicBool,
+) -> Result<(), String> {
+    av::init().map_err(|e| e.to_string())?;
+    let mut ictx = format::input(input).map_err(|e| e.to_string())?;
+
+    let (stream_index, in_time_base, mut decoder) = {
+        let stream = ictx
+            .streams()
+            .filter(|s| s.parameters().medium() == media::Type::Audio)
+            .nth(job.audio_track.unwrap_or(0))
+            .ok_or("The file has no audio stream")?;
+        let decoder = codec::context::Context::from_parameters(stream.parameters())
+            .and_then(|context| context.decoder().audio())
+            .map_err(|e| e.to_string())?;
+        (stream.index(), stream.time_base(), decoder)
+    };
+    // Progress runs over the trimmed range of the source
+    let start = job.trim_start.unwrap_or(0.0);
+    let end = job
+        .trim_end
+        .or(source_duration)
+        .or_else(|| (ictx.duration() > 0).then(|| ictx.duration() as f64 / f64::from(av::rescale::TIME_BASE)));
+
+    let codec = av::encoder::find_by_name(output_format.encoder())
+        .ok_or_else(|| format!("The built-in FFmpeg has no {} encoder", output_format.encoder()))?
+        .audio()
+        .map_err(|e| e.to_string())?;
+    let mut octx = format::output_as(output, output_format.muxer()).map_err(|e| e.to_string())?;
+    let global_header = octx.format().flags().contains(format::flag::Flags::GLOBAL_HEADER);
+
+    let mut encoder = {
+        let mut stream = octx.add_stream(codec).map_err(|e| e.to_string())?;
+        let mut encoder = codec::context::Context::from_parameters(stream.parameters())
+            .and_then(|context| context.encoder().audio())
+            .map_err(|e| e.to_string())?;
+
+        let rate = settings.sample_rate.map_or(decoder.rate() as i32, |rate| rate as i32);
+        let channels = match settings.channels {
+            Channels::Mono => 1,
+            Channels::Stereo => 2,
+            Channels::Keep => decoder.channel_layout().channels(),
+        };
+        let layout = codec
+            .channel_layouts()
+            .map(|layouts| layouts.best(channels))
+            .unwrap_or(av::ChannelLayout::default(channels));
+        let sample_format = codec
+            .formats()
+            .and_then(|mut formats| formats.next())
+            .ok_or("The encoder reports no sample formats")?;
+
+        let mut flags = codec::flag::Flags::empty();
+        if global_header {
+            flags |= codec::flag::Flags::GLOBAL_HEADER;
+        }
+        match (output_format, settings.mode) {
+            (OutputFormat::Mp3, EncodeMode::Vbr) => {
+                flags |= codec::flag::Flags::QSCALE;
+                encoder.set_quality(settings.vbr_quality as usize * QP2LAMBDA);
+            }
+            (OutputFormat::Flac, _) => {}
+            _ => encoder.set_bit_rate(settings.bitrate as usize * 1000),
+        }
+        encoder.set_flags(flags);
+        encoder.set_rate(rate);
+        encoder.set_channel_layout(layout);
+        encoder.set_format(sample_format);
+        encoder.set_time_base((1, rate));
+        stream.set_time_base((1, rate));
+
+        let encoder = encoder.open_as(codec).map_err(|e| e.to_string())?;
+        stream.set_parameters(&encoder);
+        encoder
+    };
+
+    let mut graph = filter_graph(filters, &decoder, in_time_base, &encoder).map_err(|e| e.to_string())?;
+    let filter_time_base = graph.get("out").unwrap().sink().time_base();
+    let encoder_time_base = av::Rational::new(1, encoder.rate() as i32);
+
+    let mut metadata = if settings.copy_metadata {
+        ictx.metadata().to_owned()
+    } else {
+        av::Dictionary::new()
+    };
+    for pair in job.tags.metadata_args().chunks(2) {
+        if let Some((key, value)) = pair[1].split_once('=') {
+            metadata.set(key, value);
+        }
+    }
+    octx.set_metadata(metadata);
+    octx.write_header().map_err(|e| e.to_string())?;
+    // The muxer may pick its own time base while writing the header
+    let out_time_base = octx.stream(0).unwrap().time_base();
+
+    let write_packets = |encoder: &mut av::encoder::Audio, octx: &mut format::context::Output| -> Result<(), String> {
+        let mut packet = av::Packet::empty();
+        while encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(0);
+            packet.rescale_ts(encoder_time_base, out_time_base);
+            packet.write_interleaved(octx).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    };
+    let encode_filtered = |graph: &mut filter::Graph, encoder: &mut av::encoder::Audio, octx: &mut format::context::Output| {
+        let mut filtered = frame::Audio::empty();
+        while graph.get("out").unwrap().sink().frame(&mut filtered).is_ok() {
+            let pts = filtered.pts().map(|pts| pts.rescale(filter_time_base, encoder_time_base));
+            filtered.set_pts(pts);
+            encoder.send_frame(&filtered).map_err(|e| e.to_string())?;
+            write_packets(encoder, octx)?;
+        }
+        Ok::<(), String>(())
+    };
+    let filter_decoded = |decoder: &mut av::decoder::Audio,
+                              graph: &mut filter::Graph,
+                              encoder: &mut av::encoder::Audio,
+                              octx: &mut format::context::Output| {
+        let mut decoded = frame::Audio::empty();
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            decoded.set_pts(decoded.timestamp());
+            graph.get("in").unwrap().source().add(&decoded).map_err(|e| e.to_string())?;
+            encode_filtered(graph, encoder, octx)?;
+        }
+        Ok::<(), String>(())
+    };
+
+    for (stream, packet) in ictx.packets() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_string());
+        }
+        if stream.index() != stream_index {
+            continue;
+        }
+        if let (Some(end), Some(pts)) = (end, packet.pts())
+            && end > start
+        {
+            let position = pts as f64 * f64::from(in_time_base);
+            on_progress(((position - start) / (end - start)).clamp(0.0, 1.0) as f32);
+        }
+        decoder.send_packet(&packet).map_err(|e| e.to_string())?;
+        filter_decoded(&mut decoder, &mut graph, &mut encoder, &mut octx)?;
+    }
+
+    decoder.send_eof().map_err(|e| e.to_string())?;
+    filter_decoded(&mut decoder, &mut graph, &mut encoder, &mut octx)?;
+    graph.get("in").unwrap().source().flush().map_err(|e| e.to_string())?;
+    encode_filtered(&mut graph, &mut encoder, &mut octx)?;
+    encoder.send_eof().map_err(|e| e.to_string())?;
+    write_packets(&mut encoder, &mut octx)?;
+    octx.write_trailer().map_err(|e| e.to_string())
+}
+
+/// Filter graph from the decoder's samples to what the encoder takes, running `spec` in between
+fn filter_graph(
+    spec: &str,
+    decoder: &av::decoder::Audio,
+    time_base: av::Rational,
+    encoder: &av::encoder::Audio,
+) -> Result<filter::Graph, av::Error> {
+    let mut graph = filter::Graph::new();
+    let args = format!(
+        "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        time_base,
+        decoder.rate(),
+        decoder.format().name(),
+        decoder.channel_layout().bits()
+    );
+    graph.add(&filter::find("abuffer").unwrap(), "in", &args)?;
+    graph.add(&filter::find("abuffersink").unwrap(), "out", "")?;
+    {
+        let mut out = graph.get("out").unwrap();
+        out.set_sample_format(encoder.format());
+        out.set_channel_layout(encoder.channel_layout());
+        out.set_sample_rate(encoder.rate());
+    }
+    graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+    graph.validate()?;
+
+    // Encoders like LAME take fixed-size frames
+    if let Some(codec) = encoder.codec()
+        && !codec.capabilities().contains(codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE)
+    {
+        graph.get("out").unwrap().sink().set_frame_size(encoder.frame_size());
+    }
+    Ok(graph)
+}
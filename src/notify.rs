@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+/// Show a desktop notification; clicking it opens the folder of `output` where the platform allows it
+///
+/// Runs on its own thread, since waiting for the click blocks.
+pub fn show(summary: String, body: String, output: Option<PathBuf>) {
+    std::thread::spawn(move || {
+        let mut notification = notify_rust::Notification::new();
+        notification.appname("Video to MP3").summary(&summary).body(&body);
+        if output.is_some() {
+            notification.action("default", "Open folder");
+        }
+
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                println!("Failed to show notification: {}", e);
+                return;
+            }
+        };
+        // macOS only delivers clicks to the main thread's run loop, which egui owns
+        #[cfg(not(target_os = "macos"))]
+        if let Some(output) = output {
+            handle.wait_for_action(|action| {
+                if action != "__closed" {
+                    crate::finish::reveal(&output);
+                }
+            });
+        }
+        #[cfg(target_os = "macos")]
+        let _ = (handle, output);
+    });
+}
@@ -0,0 +1,857 @@
+use crate::cue::{self, CueTrack};
+use crate::ffmpeg::{self, EqPreset, JobOptions, Loudness, ReplayGain};
+use crate::log;
+use crate::probe::{self, MediaInfo};
+use crate::progress::{Progress, ReportParser};
+use crate::settings::{Channels, Downmix, OutputFormat, Settings, SourceAction, SplitMode};
+use crate::timecode;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// Largest difference between expected and actual output length that still counts as complete
+const DURATION_TOLERANCE: f64 = 1.0;
+
+/// Start of the error returned when the watchdog stops a hung FFmpeg
+pub const STALLED: &str = "FFmpeg stalled";
+
+tokio::task_local! {
+    /// Seconds FFmpeg may go without progress before it is stopped, 0 waits forever
+    pub static STALL_TIMEOUT: u32;
+}
+
+/// Receives how far a conversion has got, from 0.0 to 1.0
+pub type ProgressFn<'a> = dyn Fn(Progress) + Send + Sync + 'a;
+
+/// Result of a finished conversion
+pub struct Conversion {
+    /// Files that were written
+    pub outputs: Vec<PathBuf>,
+    /// Set when the output looks incomplete, e.g. shorter than the source
+    pub warning: Option<String>,
+}
+
+impl Conversion {
+    /// Combine the results of several conversions into one
+    fn merge(conversions: Vec<Conversion>) -> Self {
+        let mut outputs = Vec::new();
+        let mut warnings = Vec::new();
+        for conversion in conversions {
+            outputs.extend(conversion.outputs);
+            warnings.extend(conversion.warning);
+        }
+        let warning = (!warnings.is_empty()).then(|| warnings.join("\n"));
+        Conversion { outputs, warning }
+    }
+}
+
+/// File name of `path` with `suffix` added to the stem and the extension replaced
+///
+/// Built from the OS string, so names that aren't valid Unicode survive intact.
+fn renamed(path: &Path, suffix: &str, extension: impl AsRef<OsStr>) -> OsString {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    name.push(".");
+    name.push(extension);
+    name
+}
+
+/// Output path for `format`: `output` with the format's extension, never the input itself
+pub fn output_path(input: &Path, output: &Path, format: OutputFormat) -> PathBuf {
+    let extension = format.extension();
+    let mut path = output.with_extension(extension);
+    // Transcoding an MP3 to MP3 mustn't overwrite the source
+    if path == input {
+        path.set_file_name(renamed(input, " (converted)", extension));
+    }
+    path
+}
+
+/// Every file converting `input` to `output` writes, one per output format
+pub fn output_paths(input: &Path, output: &Path, settings: &Settings) -> Vec<PathBuf> {
+    settings
+        .output_formats()
+        .into_iter()
+        .map(|format| output_path(input, output, format))
+        .collect()
+}
+
+/// Files converting `input` to `output` would replace
+pub fn existing_outputs(input: &Path, output: &Path, settings: &Settings) -> Vec<PathBuf> {
+    output_paths(input, output, settings).into_iter().filter(|path| path.exists()).collect()
+}
+
+/// `output` numbered `name (1)`, `name (2)` and so on, until it replaces no existing file
+pub fn numbered_output(input: &Path, output: &Path, settings: &Settings) -> PathBuf {
+    numbered(output, |candidate| existing_outputs(input, candidate, settings).is_empty())
+}
+
+/// The first of `path` numbered `name (1)`, `name (2)` and so on that `is_free` accepts
+pub fn numbered(path: &Path, is_free: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    (1..)
+        .map(|n| {
+            let mut name = stem.to_os_string();
+            name.push(format!(" ({})", n));
+            if let Some(extension) = path.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| is_free(candidate))
+        .unwrap()
+}
+
+/// Give `outputs` the modified, accessed and created times of `input`
+///
+/// Linux has no way to set the created time, there the outputs only get the other two.
+pub fn copy_timestamps(input: &Path, outputs: &[PathBuf]) {
+    let metadata = match fs::metadata(input) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            println!("Failed to read the times of '{}': {}", input.display(), e);
+            return;
+        }
+    };
+    let mut times = fs::FileTimes::new();
+    if let Ok(modified) = metadata.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    #[cfg(windows)]
+    if let Ok(created) = metadata.created() {
+        use std::os::windows::fs::FileTimesExt;
+        times = times.set_created(created);
+    }
+    #[cfg(target_os = "macos")]
+    if let Ok(created) = metadata.created() {
+        use std::os::macos::fs::FileTimesExt;
+        times = times.set_created(created);
+    }
+
+    for output in outputs {
+        let result = fs::File::options().write(true).open(output).and_then(|file| file.set_times(times));
+        if let Err(e) = result {
+            println!("Failed to set the times of '{}': {}", output.display(), e);
+        }
+    }
+}
+
+/// Keep, move or trash `input` as `action` says, once `conversion` has written it out
+///
+/// The source is only touched when every output is there, isn't empty and passed the
+/// length check, so a bad conversion never costs the original.
+pub fn dispose_source(input: &Path, conversion: &Conversion, action: SourceAction) {
+    if action == SourceAction::Keep {
+        return;
+    }
+    let verified = conversion.warning.is_none()
+        && !conversion.outputs.is_empty()
+        && conversion
+            .outputs
+            .iter()
+            .all(|output| output != input && fs::metadata(output).is_ok_and(|m| m.len() > 0));
+    if !verified {
+        println!("Kept '{}', the output could not be verified", input.display());
+        return;
+    }
+
+    let result = match action {
+        SourceAction::Keep => Ok(()),
+        SourceAction::MoveToConverted => move_to_converted(input),
+        SourceAction::Trash => trash::delete(input).map_err(|e| e.to_string()),
+    };
+    match result {
+        Ok(()) => println!("{}: '{}'", action.name(), input.display()),
+        Err(e) => println!("Failed to {} '{}': {}", action.name().to_lowercase(), input.display(), e),
+    }
+}
+
+/// Move `input` into a `converted` folder beside it, never replacing a file already there
+fn move_to_converted(input: &Path) -> Result<(), String> {
+    let dir = input.parent().unwrap_or(Path::new("")).join("converted");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let target = dir.join(input.file_name().unwrap_or_default());
+    if target.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    fs::rename(input, &target).map_err(|e| e.to_string())
+}
+
+/// Delete what a cancelled conversion of `input` had written since `started`
+///
+/// `all_tracks` is the source when every audio track was converted, each to its own file.
+/// Older files are left alone, they come from an earlier conversion the cancelled one never reached.
+pub fn remove_outputs(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    all_tracks: Option<&MediaInfo>,
+    started: SystemTime,
+) {
+    let outputs = match all_tracks {
+        Some(media) => track_outputs(output, media),
+        None => vec![output.to_path_buf()],
+    };
+    for path in outputs.iter().flat_map(|output| output_paths(input, output, settings)) {
+        let written = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= started);
+        if written
+            && let Err(e) = fs::remove_file(&path)
+        {
+            println!("Failed to remove '{}': {}", path.display(), e);
+        }
+        // Only this app writes `.part` files, whatever is left of one is unfinished
+        let _ = fs::remove_file(part_path(&path));
+    }
+}
+
+/// Where `output` is encoded to before it's complete, e.g. `song.mp3.part`
+///
+/// Renamed to `output` once FFmpeg succeeds, so a file under the real name is always whole.
+pub fn part_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Run every step of a conversion: optional analysis passes, then one encode per output format
+///
+/// The analysis results are shared. With several formats to encode, the source is decoded,
+/// trimmed and filtered once into a lossless intermediate that every format is encoded from,
+/// so an extra format costs an encode of already decoded audio. Formats that copy the source
+/// audio as-is still read it straight from the source.
+pub async fn run(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    mut job: JobOptions,
+    media: Option<&MediaInfo>,
+    on_progress: &ProgressFn<'_>,
+) -> Result<Conversion, String> {
+    if settings.normalize {
+        job.loudness = Some(measure_loudness(input, settings, &job, media).await?);
+    }
+
+    if settings.split == SplitMode::Silence {
+        let stderr = run_ffmpeg(&ffmpeg::silence_args(input, settings, &job, media)).await?;
+        job.split_points = ffmpeg::parse_silence(&stderr);
+        println!("Splitting at {} silence gaps", job.split_points.len());
+    }
+
+    let formats = settings.output_formats();
+    let shared = Decoded::needed(settings, &job, media);
+    // The shared decode and each format's encode cover an equal share of the progress
+    let steps = formats.len() + shared as usize;
+    let decoded = match shared {
+        true => {
+            let decoded = Decoded::new(temp_path("decoded", "mka")?, settings, &job, media);
+            let cleanup = TempFile(decoded.file.clone());
+            let args = ffmpeg::decode_args(input, &decoded.file, settings, &job, media);
+            let duration = ffmpeg::output_duration(settings, &job, media);
+            let decode_progress = |p: Progress| on_progress(p.step(0, steps));
+            run_ffmpeg_with_progress(&args, duration, &decode_progress).await?;
+            Some((decoded, cleanup))
+        }
+        false => None,
+    };
+
+    let mut conversions = Vec::new();
+    for (n, &format) in formats.iter().enumerate() {
+        let format_settings = Settings {
+            format,
+            ..settings.clone()
+        };
+        let format_output = output_path(input, output, format);
+        let format_progress = |p: Progress| on_progress(p.step(n + shared as usize, steps));
+        let conversion = match decoded {
+            Some((ref decoded, _)) if !ffmpeg::copies_stream(&format_settings, &job, media) => {
+                let decoded_settings = decoded.settings_for(format);
+                encode(&decoded.file, &format_output, &decoded_settings, decoded.job.clone(), Some(&decoded.media), &format_progress)
+                    .await?
+            }
+            _ => encode(input, &format_output, &format_settings, job.clone(), media, &format_progress).await?,
+        };
+        conversions.push(conversion);
+    }
+    drop(decoded);
+
+    // The cue sheet points at one file, the primary format's
+    if settings.write_cue && !is_splitting(settings, &job) {
+        write_cue(input, &output_path(input, output, settings.format), settings, &job, media).await?;
+    }
+
+    // One picture per file is enough, the extra formats hold the same audio
+    if settings.spectrogram
+        && let Some(conversion) = conversions.first()
+    {
+        for file in &conversion.outputs {
+            // `showspectrumpic` only outputs its one frame at the end, the watchdog would take that for a stall
+            STALL_TIMEOUT
+                .scope(0, run_ffmpeg(&ffmpeg::spectrogram_args(file, &file.with_extension("png"))))
+                .await?;
+        }
+    }
+
+    if settings.export_subtitles
+        && let Some(media) = media
+    {
+        export_subtitles(input, output, settings, &job, media).await?;
+    }
+
+    Ok(Conversion::merge(conversions))
+}
+
+/// Lossless file holding the source audio already trimmed and filtered
+struct Decoded {
+    file: PathBuf,
+    /// Settings for encoding from `file`, with the filters it already went through switched off
+    settings: Settings,
+    /// Job for encoding from `file`, whose only stream starts at the trimmed start
+    job: JobOptions,
+    /// The source as `file` holds it: output length, chapters in output time
+    media: MediaInfo,
+}
+
+impl Decoded {
+    /// Whether more than one format has to be encoded rather than copied from the source
+    fn needed(settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> bool {
+        let encoded = settings
+            .output_formats()
+            .into_iter()
+            .filter(|&format| {
+                let settings = Settings {
+                    format,
+                    ..settings.clone()
+                };
+                !ffmpeg::copies_stream(&settings, job, media)
+            })
+            .count();
+        encoded > 1
+    }
+
+    fn new(file: PathBuf, settings: &Settings, job: &JobOptions, media: Option<&MediaInfo>) -> Self {
+        let offset = job.trim_start.unwrap_or(0.0);
+        let mut decoded_media = media.cloned().unwrap_or_default();
+        decoded_media.duration = ffmpeg::output_duration(settings, job, media);
+        for chapter in &mut decoded_media.chapters {
+            chapter.start = (chapter.start - offset) / settings.speed as f64;
+        }
+        let settings = Settings {
+            normalize: false,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            speed: 1.0,
+            sample_rate: None,
+            channels: Channels::Keep,
+            downmix: Downmix::Default,
+            hardware_decode: false,
+            stream_copy: false,
+            ..settings.clone()
+        };
+        let job = JobOptions {
+            trim_start: None,
+            trim_end: None,
+            audio_track: None,
+            loudness: None,
+            audio_offset_ms: 0,
+            eq: EqPreset::Flat,
+            ..job.clone()
+        };
+        Self {
+            file,
+            settings,
+            job,
+            media: decoded_media,
+        }
+    }
+
+    /// Settings to encode `format` from `file` with
+    fn settings_for(&self, format: OutputFormat) -> Settings {
+        Settings {
+            format,
+            ..self.settings.clone()
+        }
+    }
+}
+
+/// Temporary file removed when dropped, also when the conversion is cancelled mid-way
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Encode to one format, then run the post-processing steps on what was written
+async fn encode(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    mut job: JobOptions,
+    media: Option<&MediaInfo>,
+    on_progress: &ProgressFn<'_>,
+) -> Result<Conversion, String> {
+    if is_splitting(settings, &job) {
+        job.segment_list = Some(temp_path("segments", "txt")?);
+        // The segment muxer can't carry an attached picture into every part
+        job.cover = None;
+    }
+
+    if needs_chapters(settings, media)
+        && let Some(duration) = ffmpeg::output_duration(settings, &job, media)
+    {
+        let file = temp_path("chapters", "txt")?;
+        let metadata = ffmpeg::chapters_metadata(duration, settings.chapter_minutes as f64 * 60.0);
+        fs::write(&file, metadata).map_err(|e| e.to_string())?;
+        job.chapters_file = Some(file);
+    }
+
+    // Split parts are named by the segment muxer, they are written in place
+    let target = match job.segment_list {
+        Some(_) => output.to_path_buf(),
+        None => part_path(output),
+    };
+    let duration = ffmpeg::output_duration(settings, &job, media);
+    let args = ffmpeg::conversion_args(input, &target, settings, &job, media);
+    let result = run_ffmpeg_with_progress(&args, duration, on_progress).await;
+    if let Some(ref file) = job.chapters_file {
+        let _ = fs::remove_file(file);
+    }
+    if target != output {
+        if result.is_ok() {
+            fs::rename(&target, output).map_err(|e| e.to_string())?;
+        } else {
+            let _ = fs::remove_file(&target);
+        }
+    }
+    result?;
+
+    let outputs = match job.segment_list.take() {
+        Some(list) => {
+            let contents = fs::read_to_string(&list).map_err(|e| e.to_string())?;
+            let _ = fs::remove_file(&list);
+            // Entries may be bare filenames, the parts always sit next to `output`
+            contents
+                .lines()
+                .filter_map(|line| Path::new(line.trim()).file_name())
+                .map(|name| output.with_file_name(name))
+                .collect()
+        }
+        None => vec![output.to_path_buf()],
+    };
+
+    // ReplayGain TXXX frames only exist in ID3
+    if settings.replaygain && settings.format == OutputFormat::Mp3 {
+        for file in &outputs {
+            write_replaygain(file).await?;
+        }
+    }
+
+    let warning = match ffmpeg::output_duration(settings, &job, media) {
+        Some(expected) => verify_duration(&outputs, expected).await,
+        None => None,
+    };
+    Ok(Conversion { outputs, warning })
+}
+
+/// Convert every audio stream of `input` into its own file
+///
+/// Files are named after `output` with the track's language appended, e.g. `name.eng.mp3`.
+pub async fn run_all_tracks(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    job: JobOptions,
+    media: &MediaInfo,
+    on_progress: &ProgressFn<'_>,
+) -> Result<Conversion, String> {
+    let track_outputs = track_outputs(output, media);
+
+    // Subtitles belong to the video, not a track, so they are exported once at the end
+    let track_settings = Settings {
+        export_subtitles: false,
+        ..settings.clone()
+    };
+
+    let mut conversions = Vec::new();
+    for (n, track_output) in track_outputs.iter().enumerate() {
+        let track_job = JobOptions {
+            audio_track: Some(n),
+            ..job.clone()
+        };
+        let track_progress = |p: Progress| on_progress(p.step(n, track_outputs.len()));
+        conversions.push(run(input, track_output, &track_settings, track_job, Some(media), &track_progress).await?);
+    }
+
+    if settings.export_subtitles {
+        export_subtitles(input, output, settings, &job, media).await?;
+    }
+
+    Ok(Conversion::merge(conversions))
+}
+
+/// Output of each audio stream of `media` for `run_all_tracks`, e.g. `name.eng.mp3`
+fn track_outputs(output: &Path, media: &MediaInfo) -> Vec<PathBuf> {
+    let languages: Vec<Option<String>> = media.audio_streams().map(|s| s.tags.language.clone()).collect();
+    let extension = output.extension().unwrap_or_default();
+    language_suffixes(&languages)
+        .into_iter()
+        .map(|suffix| output.with_file_name(renamed(output, &format!(".{}", suffix), extension)))
+        .collect()
+}
+
+/// Filename suffixes telling streams apart, the language code where there is one
+///
+/// Streams without a language get their 1-based position, repeated languages get it appended.
+fn language_suffixes(languages: &[Option<String>]) -> Vec<String> {
+    let mut used = HashSet::new();
+    languages
+        .iter()
+        .enumerate()
+        .map(|(n, language)| {
+            let suffix = language.clone().unwrap_or_else(|| (n + 1).to_string());
+            if used.insert(suffix.clone()) {
+                suffix
+            } else {
+                format!("{}.{}", suffix, n + 1)
+            }
+        })
+        .collect()
+}
+
+/// The FFmpeg commands `run` would execute, quoted for a shell
+///
+/// Analysis results aren't known yet, so measured loudness and silence split points
+/// are left out and temporary files show up under placeholder names.
+pub fn preview(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    mut job: JobOptions,
+    media: Option<&MediaInfo>,
+) -> Vec<String> {
+    let mut commands = Vec::new();
+    if settings.normalize {
+        commands.push(ffmpeg::loudness_args(input, settings, &job, media));
+    }
+    let has_chapters = media.is_some_and(|m| !m.chapters.is_empty());
+    let cue_from_silence = settings.write_cue && settings.split == SplitMode::None && !has_chapters;
+    if settings.split == SplitMode::Silence {
+        commands.push(ffmpeg::silence_args(input, settings, &job, media));
+    }
+
+    let temp_dir = std::env::temp_dir().join("vid2mp3");
+    let decoded = Decoded::needed(settings, &job, media).then(|| {
+        let decoded = Decoded::new(temp_dir.join("decoded.mka"), settings, &job, media);
+        commands.push(ffmpeg::decode_args(input, &decoded.file, settings, &job, media));
+        decoded
+    });
+    if is_splitting(settings, &job) {
+        job.segment_list = Some(temp_dir.join("segments.txt"));
+        job.cover = None;
+    }
+    for (n, format) in settings.output_formats().into_iter().enumerate() {
+        let settings = Settings {
+            format,
+            ..settings.clone()
+        };
+        let output = output_path(input, output, format);
+        let mut job = job.clone();
+        if needs_chapters(&settings, media) {
+            job.chapters_file = Some(temp_dir.join("chapters.txt"));
+        }
+        let args = match decoded {
+            Some(ref decoded) if !ffmpeg::copies_stream(&settings, &job, media) => {
+                let mut decoded_job = decoded.job.clone();
+                decoded_job.segment_list = job.segment_list.clone();
+                decoded_job.cover = job.cover.clone();
+                decoded_job.chapters_file = job.chapters_file.clone();
+                ffmpeg::conversion_args(&decoded.file, &output, &decoded.settings_for(format), &decoded_job, Some(&decoded.media))
+            }
+            _ => ffmpeg::conversion_args(input, &output, &settings, &job, media),
+        };
+        commands.push(args);
+        if cue_from_silence && n == 0 {
+            commands.push(ffmpeg::silence_args(input, &settings, &job, media));
+        }
+        if settings.replaygain && settings.format == OutputFormat::Mp3 {
+            commands.push(ffmpeg::replaygain_args(&output));
+        }
+        if settings.spectrogram && n == 0 {
+            commands.push(ffmpeg::spectrogram_args(&output, &output.with_extension("png")));
+        }
+    }
+
+    commands
+        .iter()
+        .map(|args| format!("ffmpeg {}", ffmpeg::command_line(args)))
+        .collect()
+}
+
+/// Whether the encode writes several parts instead of one file
+fn is_splitting(settings: &Settings, job: &JobOptions) -> bool {
+    match settings.split {
+        SplitMode::None => false,
+        SplitMode::Silence => !job.split_points.is_empty(),
+        SplitMode::Fixed => true,
+    }
+}
+
+/// Audiobooks get evenly spaced chapters when the source has none to carry over
+fn needs_chapters(settings: &Settings, media: Option<&MediaInfo>) -> bool {
+    let has_chapters = media.is_some_and(|m| !m.chapters.is_empty());
+    settings.format == OutputFormat::M4b && !has_chapters && settings.chapter_minutes > 0
+}
+
+/// Unique path in the app's temp directory
+fn temp_path(prefix: &str, extension: &str) -> Result<PathBuf, String> {
+    let temp_dir = std::env::temp_dir().join("vid2mp3");
+    fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
+    let timestamp = SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    Ok(temp_dir.join(format!("{}_{}.{}", prefix, timestamp, extension)))
+}
+
+/// Run FFmpeg to completion, returning its stderr on success
+async fn run_ffmpeg(args: &[OsString]) -> Result<String, String> {
+    run_ffmpeg_with_progress(args, None, &|_| {}).await
+}
+
+/// Run FFmpeg to completion like `run_ffmpeg`, reporting progress against `duration` seconds of output
+///
+/// The command and everything FFmpeg prints go to the log as they come in.
+async fn run_ffmpeg_with_progress(
+    args: &[OsString],
+    duration: Option<f64>,
+    on_progress: &ProgressFn<'_>,
+) -> Result<String, String> {
+    log::append(format!("$ ffmpeg {}", ffmpeg::command_line(args)));
+    let mut child = ffmpeg::command()
+        .args(["-progress", "pipe:1", "-nostats"])
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not start FFmpeg: {}", e))?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    // Last time the output position moved, FFmpeg keeps writing progress blocks while it is stuck
+    let last_progress = Mutex::new((Instant::now(), None));
+
+    // `-progress pipe:1` keeps the reports on stdout, apart from the log text on stderr
+    let read_progress = async {
+        let mut parser = ReportParser::default();
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some(report) = parser.feed(&line) else {
+                continue;
+            };
+            {
+                let mut last = last_progress.lock().unwrap();
+                if last.1 != report.out_time {
+                    *last = (Instant::now(), report.out_time);
+                }
+            }
+            if let Some(progress) = report.progress(duration) {
+                on_progress(progress);
+            }
+        }
+    };
+    // stderr has to be drained at the same time, or FFmpeg blocks once the pipe fills up
+    let read_stderr = async {
+        let mut text = String::new();
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            text.push_str(&line);
+            text.push('\n');
+            log::append(line);
+        }
+        text
+    };
+    let timeout = STALL_TIMEOUT.try_with(|secs| *secs).unwrap_or(0);
+    let watchdog = async {
+        if timeout == 0 {
+            std::future::pending::<()>().await;
+        }
+        while last_progress.lock().unwrap().0.elapsed() < Duration::from_secs(timeout.into()) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    };
+    let stderr = tokio::select! {
+        ((), stderr) = async { tokio::join!(read_progress, read_stderr) } => stderr,
+        _ = watchdog => {
+            let _ = child.kill().await;
+            log::append(format!("No progress for {} s, stopped FFmpeg", timeout));
+            return Err(format!("{}: no progress for {} seconds, so it was stopped", STALLED, timeout));
+        }
+    };
+
+    let status = child.wait().await.map_err(|e| e.to_string())?;
+    if status.success() {
+        on_progress(Progress::at(1.0));
+        Ok(stderr)
+    } else {
+        Err(stderr)
+    }
+}
+
+/// Post-processing: measure `file` and add ReplayGain tags without re-encoding
+async fn write_replaygain(file: &Path) -> Result<(), String> {
+    let stderr = run_ffmpeg(&ffmpeg::replaygain_args(file)).await?;
+    let replaygain =
+        ReplayGain::parse(&stderr).ok_or_else(|| format!("Could not read ReplayGain analysis:\n{}", stderr))?;
+
+    // Remux next to the original so the final rename stays on one filesystem
+    let mut tagged_name = file.file_stem().unwrap_or_default().to_os_string();
+    tagged_name.push(".replaygain.");
+    tagged_name.push(file.extension().unwrap_or_default());
+    let tagged = file.with_file_name(tagged_name);
+
+    if let Err(e) = run_ffmpeg(&ffmpeg::replaygain_tag_args(file, &tagged, &replaygain)).await {
+        let _ = fs::remove_file(&tagged);
+        return Err(e);
+    }
+    fs::rename(&tagged, file).map_err(|e| e.to_string())
+}
+
+/// Side output: write each text subtitle stream as an .srt next to `output`
+///
+/// A single stream becomes `name.srt`, several are told apart by language, e.g. `name.eng.srt`.
+async fn export_subtitles(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    job: &JobOptions,
+    media: &MediaInfo,
+) -> Result<(), String> {
+    let subtitles = media.text_subtitles();
+    let languages: Vec<Option<String>> = subtitles.iter().map(|(_, s)| s.tags.language.clone()).collect();
+
+    for ((index, _), suffix) in subtitles.iter().zip(language_suffixes(&languages)) {
+        let name = if subtitles.len() == 1 {
+            renamed(output, "", "srt")
+        } else {
+            renamed(output, &format!(".{}", suffix), "srt")
+        };
+        let path = output.with_file_name(name);
+        run_ffmpeg(&ffmpeg::subtitle_args(input, &path, settings, job, *index))
+            .await
+            .map_err(|e| format!("Subtitle export failed:\n{}", e))?;
+        println!("Subtitles written to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Side output: a cue sheet next to a single `output`, so players can still jump between tracks
+///
+/// Tracks come from the source's chapters, or from gaps of silence when it has none.
+async fn write_cue(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    job: &JobOptions,
+    media: Option<&MediaInfo>,
+) -> Result<(), String> {
+    // Chapters are in source time, move them onto the trimmed and sped-up output
+    let offset = job.trim_start.unwrap_or(0.0);
+    let length = ffmpeg::output_duration(settings, job, media);
+    let mut tracks = Vec::new();
+    for chapter in media.map(|m| m.chapters.as_slice()).unwrap_or_default() {
+        let start = (chapter.start - offset) / settings.speed as f64;
+        if length.is_some_and(|l| start >= l) {
+            break;
+        }
+        // The chapter playing when the output starts becomes the first track
+        if start <= 0.0 {
+            tracks.clear();
+        }
+        tracks.push(CueTrack {
+            start: start.max(0.0),
+            title: chapter.title.clone(),
+        });
+    }
+
+    if tracks.is_empty() {
+        let stderr = run_ffmpeg(&ffmpeg::silence_args(input, settings, job, media)).await?;
+        tracks = std::iter::once(0.0)
+            .chain(ffmpeg::parse_silence(&stderr))
+            .map(|start| CueTrack { start, title: None })
+            .collect();
+    }
+
+    if tracks.len() < 2 {
+        println!("No chapters or silence gaps found, skipping cue sheet");
+        return Ok(());
+    }
+
+    // Fall back to the source's tags for what the user left empty
+    let tag = |value: &str, key: &str| {
+        let value = value.trim();
+        if value.is_empty() && settings.copy_metadata {
+            media.and_then(|m| m.tags.get(key)).cloned().unwrap_or_default()
+        } else {
+            value.to_string()
+        }
+    };
+    let album = tag(&job.tags.album, "album");
+    let title = if album.is_empty() { tag(&job.tags.title, "title") } else { album };
+    let performer = tag(&job.tags.artist, "artist");
+
+    let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+    let file_type = match settings.format {
+        OutputFormat::Mp3 => "MP3",
+        _ => "WAVE",
+    };
+    let sheet = cue::cue_sheet(&file_name, file_type, &title, &performer, &tracks);
+    fs::write(output.with_extension("cue"), sheet).map_err(|e| e.to_string())
+}
+
+/// Probe the written files and compare their total length against `expected` seconds
+///
+/// Returns a warning when they differ by more than a second, which usually means the
+/// source was truncated or damaged.
+async fn verify_duration(outputs: &[PathBuf], expected: f64) -> Option<String> {
+    let mut actual = 0.0;
+    for output in outputs {
+        match probe::probe(output).await {
+            Ok(MediaInfo { duration: Some(duration), .. }) => actual += duration,
+            _ => return Some(format!("Could not read the length of {}", output.display())),
+        }
+    }
+
+    if (actual - expected).abs() > DURATION_TOLERANCE {
+        Some(format!(
+            "Output is {} long but {} was expected",
+            timecode::format(actual),
+            timecode::format(expected)
+        ))
+    } else {
+        None
+    }
+}
+
+/// First `loudnorm` pass: analyze the input without writing any output
+async fn measure_loudness(
+    input: &Path,
+    settings: &Settings,
+    job: &JobOptions,
+    media: Option<&MediaInfo>,
+) -> Result<Loudness, String> {
+    let stderr = run_ffmpeg(&ffmpeg::loudness_args(input, settings, job, media)).await?;
+    Loudness::parse(&stderr).ok_or_else(|| format!("Could not read loudness measurement:\n{}", stderr))
+}
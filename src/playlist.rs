@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+/// Playlist formats whose entries can be queued
+const PLAYLIST_EXTENSIONS: [&str; 2] = ["m3u", "m3u8"];
+
+/// Whether `path` looks like an M3U playlist
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| PLAYLIST_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Local files listed in an M3U playlist, in playlist order
+///
+/// Comment and `#EXT` lines are skipped, relative entries are resolved against the
+/// playlist's folder and URLs other than `file://` are left out.
+pub fn read(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let text = String::from_utf8_lossy(&bytes);
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = match line.strip_prefix("file://").map(percent_decode) {
+            // `file:///C:/...` keeps a leading slash before the drive letter
+            Some(rest) if cfg!(windows) => PathBuf::from(rest.trim_start_matches('/')),
+            Some(rest) => PathBuf::from(rest),
+            None if line.contains("://") => {
+                println!("Skipping playlist entry '{}', only local files are supported", line);
+                continue;
+            }
+            None => PathBuf::from(line),
+        };
+        entries.push(if entry.is_absolute() { entry } else { dir.join(entry) });
+    }
+    Ok(entries)
+}
+
+/// Undo URL escapes like `%20` in a `file://` entry
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_playlist(contents: &str) -> (tempfile::TempDir, Vec<PathBuf>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.m3u8");
+        std::fs::write(&path, contents).unwrap();
+        let entries = read(&path).unwrap();
+        (dir, entries)
+    }
+
+    #[test]
+    fn relative_entries_resolve_against_playlist_folder() {
+        let (dir, entries) = read_playlist("intro.mp4\nextras/outro.mkv\n");
+        assert_eq!(entries, [dir.path().join("intro.mp4"), dir.path().join("extras/outro.mkv")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_urls_are_decoded() {
+        let (_dir, entries) = read_playlist("file:///home/user/My%20Videos/clip%201.mp4\n");
+        assert_eq!(entries, [PathBuf::from("/home/user/My Videos/clip 1.mp4")]);
+    }
+
+    #[test]
+    fn byte_order_mark_and_extinf_lines_are_skipped() {
+        let (dir, entries) = read_playlist("\u{feff}#EXTM3U\n#EXTINF:123,Artist - Title\nsong.mp4\n\n# comment\n");
+        assert_eq!(entries, [dir.path().join("song.mp4")]);
+    }
+
+    #[test]
+    fn remote_entries_are_skipped() {
+        let (dir, entries) = read_playlist("http://example.com/stream.mp4\nhttps://example.com/a.mp4\nlocal.mp4\n");
+        assert_eq!(entries, [dir.path().join("local.mp4")]);
+    }
+
+    #[test]
+    fn percent_decode_keeps_invalid_escapes() {
+        assert_eq!(percent_decode("a%20b"), "a b");
+        assert_eq!(percent_decode("100%zz"), "100%zz");
+        assert_eq!(percent_decode("end%2"), "end%2");
+        assert_eq!(percent_decode("%C3%BCber"), "über");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::settings::{Channels, Downmix, EncodeMode, OutputFormat, Settings};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A named bundle of encoding settings
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    pub format: OutputFormat,
+    pub extra_formats: Vec<OutputFormat>,
+    pub mode: EncodeMode,
+    pub bitrate: u32,
+    pub auto_bitrate: bool,
+    pub vbr_quality: u8,
+    pub sample_rate: Option<u32>,
+    pub channels: Channels,
+    pub downmix: Downmix,
+    pub normalize: bool,
+    pub loudness_target: f32,
+    pub fade_in: f32,
+    pub fade_out: f32,
+    pub speed: f32,
+    pub preserve_pitch: bool,
+    pub extra_args: String,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self::from_settings("Standard", &Settings::default())
+    }
+}
+
+impl Preset {
+    /// Presets that ship with the app
+    pub fn built_in() -> Vec<Preset> {
+        let with_bitrate = |name: &str, bitrate: u32| Preset {
+            name: name.to_string(),
+            bitrate,
+            ..Default::default()
+        };
+        vec![
+            with_bitrate("Low", 96),
+            with_bitrate("Standard", 192),
+            with_bitrate("High", 320),
+            Preset {
+                channels: Channels::Mono,
+                normalize: true,
+                ..with_bitrate("Podcast mono 96k", 96)
+            },
+            Preset {
+                channels: Channels::Stereo,
+                ..with_bitrate("Music 320k", 320)
+            },
+            // Speech needs little bandwidth, levelling matters more
+            Preset {
+                channels: Channels::Mono,
+                sample_rate: Some(22050),
+                normalize: true,
+                ..with_bitrate("Voice memo", 64)
+            },
+        ]
+    }
+
+    /// Read a preset exported with `save`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    /// Write this preset to its own file, to share it or keep a copy
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Capture the encoding part of `settings` under `name`
+    pub fn from_settings(name: &str, settings: &Settings) -> Self {
+        Self {
+            name: name.to_string(),
+            format: settings.format,
+            extra_formats: settings.extra_formats.clone(),
+            mode: settings.mode,
+            bitrate: settings.bitrate,
+            auto_bitrate: settings.auto_bitrate,
+            vbr_quality: settings.vbr_quality,
+            sample_rate: settings.sample_rate,
+            channels: settings.channels,
+            downmix: settings.downmix,
+            normalize: settings.normalize,
+            loudness_target: settings.loudness_target,
+            fade_in: settings.fade_in,
+            fade_out: settings.fade_out,
+            speed: settings.speed,
+            preserve_pitch: settings.preserve_pitch,
+            extra_args: settings.extra_args.clone(),
+        }
+    }
+
+    /// Overwrite the encoding part of `settings` with this preset
+    pub fn apply(&self, settings: &mut Settings) {
+        settings.format = self.format;
+        settings.extra_formats = self.extra_formats.clone();
+        settings.mode = self.mode;
+        settings.bitrate = self.bitrate;
+        settings.auto_bitrate = self.auto_bitrate;
+        settings.vbr_quality = self.vbr_quality;
+        settings.sample_rate = self.sample_rate;
+        settings.channels = self.channels;
+        settings.downmix = self.downmix;
+        settings.normalize = self.normalize;
+        settings.loudness_target = self.loudness_target;
+        settings.fade_in = self.fade_in;
+        settings.fade_out = self.fade_out;
+        settings.speed = self.speed;
+        settings.preserve_pitch = self.preserve_pitch;
+        settings.extra_args = self.extra_args.clone();
+    }
+
+    /// Whether `settings` currently encode exactly like this preset
+    pub fn matches(&self, settings: &Settings) -> bool {
+        Self::from_settings(&self.name, settings) == *self
+    }
+}
@@ -0,0 +1,204 @@
+use crate::ffmpeg;
+use crate::settings::OutputFormat;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Subtitle codecs FFmpeg can convert to SubRip
+const TEXT_SUBTITLE_CODECS: [&str; 6] = ["subrip", "ass", "ssa", "mov_text", "webvtt", "text"];
+
+/// Codecs that store audio without loss, so any bitrate is an improvement
+const LOSSLESS_CODECS: [&str; 4] = ["flac", "alac", "wavpack", "truehd"];
+
+/// Media information reported by ffprobe
+#[derive(Clone, Default)]
+pub struct MediaInfo {
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    /// Container format, e.g. `QuickTime / MOV`
+    pub container: Option<String>,
+    /// Overall bitrate in kbps
+    pub bitrate_kbps: Option<u32>,
+    /// Container-level tags, keys lowercased
+    pub tags: HashMap<String, String>,
+    pub chapters: Vec<Chapter>,
+    pub streams: Vec<StreamInfo>,
+}
+
+/// A chapter marker in the source
+#[derive(Clone)]
+pub struct Chapter {
+    /// Start time in seconds
+    pub start: f64,
+    pub title: Option<String>,
+}
+
+/// A single stream of the probed file
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StreamInfo {
+    pub index: usize,
+    pub codec_type: String,
+    pub codec_name: String,
+    pub channels: Option<u32>,
+    sample_rate: Option<String>,
+    bit_rate: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    pub tags: StreamTags,
+}
+
+/// Stream tags we care about
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StreamTags {
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+impl StreamInfo {
+    /// Human-readable description, e.g. `eng · ac3 5.1 · Commentary`
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ref language) = self.tags.language {
+            parts.push(language.clone());
+        }
+        let layout = match self.channels {
+            Some(1) => " mono".to_string(),
+            Some(2) => " stereo".to_string(),
+            Some(6) => " 5.1".to_string(),
+            Some(8) => " 7.1".to_string(),
+            Some(n) => format!(" {}ch", n),
+            None => String::new(),
+        };
+        parts.push(format!("{}{}", self.codec_name, layout));
+        if let Some(ref title) = self.tags.title {
+            parts.push(title.clone());
+        }
+        parts.join(" · ")
+    }
+
+    /// Sample rate in Hz, for audio streams
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate.as_deref().and_then(|r| r.parse().ok())
+    }
+
+    /// Bitrate in kbps, when the container reports one for this stream
+    pub fn bitrate_kbps(&self) -> Option<u32> {
+        let bits: u32 = self.bit_rate.as_deref()?.parse().ok()?;
+        Some(bits / 1000)
+    }
+
+    /// Frames per second of a video stream, from a fraction like `30000/1001`
+    pub fn fps(&self) -> Option<f64> {
+        let (num, den) = self.avg_frame_rate.as_deref()?.split_once('/')?;
+        let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+        (num > 0.0 && den > 0.0).then(|| num / den)
+    }
+
+    /// Whether the stream is uncompressed or losslessly compressed
+    pub fn is_lossless(&self) -> bool {
+        self.codec_name.starts_with("pcm_") || LOSSLESS_CODECS.contains(&self.codec_name.as_str())
+    }
+}
+
+impl MediaInfo {
+    /// Audio streams in container order
+    pub fn audio_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(|s| s.codec_type == "audio")
+    }
+
+    /// The `track`th audio stream, or the first one when no track is picked
+    pub fn audio_stream(&self, track: Option<usize>) -> Option<&StreamInfo> {
+        self.audio_streams().nth(track.unwrap_or(0))
+    }
+
+    /// Text subtitle streams, with their position among all subtitle streams
+    ///
+    /// Bitmap subtitles (DVD, Blu-ray) are left out since they can't become SRT.
+    pub fn text_subtitles(&self) -> Vec<(usize, &StreamInfo)> {
+        self.streams
+            .iter()
+            .filter(|s| s.codec_type == "subtitle")
+            .enumerate()
+            .filter(|(_, s)| TEXT_SUBTITLE_CODECS.contains(&s.codec_name.as_str()))
+            .collect()
+    }
+
+    /// Whether the selected audio stream has more than two channels
+    pub fn is_surround(&self, track: Option<usize>) -> bool {
+        self.audio_stream(track).and_then(|s| s.channels).is_some_and(|c| c > 2)
+    }
+
+    /// Whether the selected audio stream already has `format`'s codec and can be copied as-is,
+    /// e.g. MP3 into `.mp3` or AAC into `.m4b`
+    pub fn can_copy_to(&self, format: OutputFormat, track: Option<usize>) -> bool {
+        self.audio_stream(track).is_some_and(|s| s.codec_name == format.codec())
+    }
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    format: ProbeFormat,
+    #[serde(default)]
+    streams: Vec<StreamInfo>,
+    #[serde(default)]
+    chapters: Vec<ProbeChapter>,
+}
+
+#[derive(Deserialize)]
+struct ProbeChapter {
+    start_time: String,
+    #[serde(default)]
+    tags: StreamTags,
+}
+
+#[derive(Default, Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    format_name: Option<String>,
+    format_long_name: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Run ffprobe on `path` and parse its JSON output
+pub async fn probe(path: &Path) -> Result<MediaInfo, String> {
+    let output = ffmpeg::probe_command()
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams", "-show_chapters"])
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| format!("Could not start ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    let parsed: ProbeOutput = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    Ok(MediaInfo {
+        duration: parsed.format.duration.and_then(|d| d.parse().ok()),
+        container: parsed.format.format_long_name.or(parsed.format.format_name),
+        bitrate_kbps: parsed.format.bit_rate.and_then(|b| b.parse::<u32>().ok()).map(|b| b / 1000),
+        tags: parsed
+            .format
+            .tags
+            .into_iter()
+            .map(|(key, value)| (key.to_lowercase(), value))
+            .collect(),
+        streams: parsed.streams,
+        chapters: parsed
+            .chapters
+            .into_iter()
+            .filter_map(|c| {
+                Some(Chapter {
+                    start: c.start_time.parse().ok()?,
+                    title: c.tags.title,
+                })
+            })
+            .collect(),
+    })
+}
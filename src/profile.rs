@@ -0,0 +1,39 @@
+use crate::preset::Preset;
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A named workflow: encoding settings plus where outputs go and how batches name them
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub preset: Preset,
+    pub output_dir: Option<PathBuf>,
+    pub batch_template: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::from_settings("Default", &Settings::default())
+    }
+}
+
+impl Profile {
+    /// Capture the parts of `settings` a profile carries under `name`
+    pub fn from_settings(name: &str, settings: &Settings) -> Self {
+        Self {
+            name: name.to_string(),
+            preset: Preset::from_settings(name, settings),
+            output_dir: settings.output_dir.clone(),
+            batch_template: settings.batch_template.clone(),
+        }
+    }
+
+    /// Overwrite the parts of `settings` this profile carries
+    pub fn apply(&self, settings: &mut Settings) {
+        self.preset.apply(settings);
+        settings.output_dir = self.output_dir.clone();
+        settings.batch_template = self.batch_template.clone();
+    }
+}
@@ -0,0 +1,66 @@
+/// How far a conversion has got, as shown in the UI
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    /// Share of the conversion that's done, 0.0 to 1.0
+    pub fraction: f32,
+    /// Encoding speed as a multiple of playback speed, e.g. 12.5 for `12.5x`
+    pub speed: Option<f32>,
+}
+
+impl Progress {
+    pub fn at(fraction: f32) -> Self {
+        Self { fraction, speed: None }
+    }
+
+    /// This progress as step `n` of `count` equal steps, e.g. one of several output formats
+    pub fn step(self, n: usize, count: usize) -> Self {
+        Self {
+            fraction: (n as f32 + self.fraction) / count as f32,
+            ..self
+        }
+    }
+}
+
+/// One block of FFmpeg's `-progress` output, which ends with a `progress=` line
+#[derive(Clone, Default)]
+pub struct Report {
+    /// Position in the output, in seconds
+    pub out_time: Option<f64>,
+    pub speed: Option<f32>,
+}
+
+impl Report {
+    /// Progress against `duration` seconds of output, `None` when either isn't known yet
+    pub fn progress(&self, duration: Option<f64>) -> Option<Progress> {
+        let fraction = (self.out_time? / duration?).clamp(0.0, 1.0) as f32;
+        Some(Progress {
+            fraction,
+            speed: self.speed,
+        })
+    }
+}
+
+/// Collects `-progress` key=value lines into reports
+///
+/// Values FFmpeg can't fill in yet are `N/A` and stay `None`. The numbers are
+/// written the same way whatever the locale, unlike the stats line on stderr.
+#[derive(Default)]
+pub struct ReportParser {
+    current: Report,
+}
+
+impl ReportParser {
+    /// Take one line, returning the report it completes
+    pub fn feed(&mut self, line: &str) -> Option<Report> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            // `out_time_ms` is microseconds too, `out_time_us` is the correctly named one
+            "out_time_us" => self.current.out_time = value.parse::<f64>().ok().map(|micros| micros / 1_000_000.0),
+            "speed" => self.current.speed = value.trim_end_matches('x').parse().ok(),
+            "progress" => return Some(std::mem::take(&mut self.current)),
+            _ => {}
+        }
+        None
+    }
+}
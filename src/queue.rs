@@ -0,0 +1,684 @@
+use crate::converter;
+use crate::ffmpeg::{self, JobOptions};
+use crate::i18n::{tr, tr_args};
+use crate::pipeline::{self, Conversion, ProgressFn};
+use crate::probe;
+use crate::progress::Progress;
+use crate::settings::{self, ExistingOutput, OutputFormat, Settings};
+use crate::tags::Tags;
+use crate::timecode;
+use chrono::{DateTime, Days, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Files waiting for, going through or done with batch conversion
+pub type Queue = Arc<Mutex<Vec<QueueItem>>>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Where a queued file is in its conversion
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ItemState {
+    Waiting,
+    Converting,
+    /// Converted, with the files that were written
+    Done(Vec<PathBuf>),
+    /// Converted but the output doesn't look complete
+    Suspicious(Vec<PathBuf>, String),
+    Failed(String),
+    /// Not converted, its output was already there
+    Skipped,
+}
+
+/// Settings changed for a single queue item, empty fields use the batch settings
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Overrides {
+    pub bitrate: Option<u32>,
+    /// Trim times as typed, parsed when the item is converted
+    pub trim_start: String,
+    pub trim_end: String,
+    /// Output filename without extension
+    pub output_name: String,
+}
+
+impl Overrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Change `settings` and `job` for this item
+    fn apply(&self, settings: &mut Settings, job: &mut JobOptions) -> Result<(), String> {
+        if let Some(bitrate) = self.bitrate {
+            settings.bitrate = bitrate;
+            settings.auto_bitrate = false;
+        }
+        let parse_field = |text: &str, name: &str| {
+            if text.trim().is_empty() {
+                Ok(None)
+            } else {
+                timecode::parse(text)
+                    .map(Some)
+                    .ok_or_else(|| tr_args("Invalid {field} time", &[("field", &tr(name))]))
+            }
+        };
+        job.trim_start = parse_field(&self.trim_start, "start")?;
+        job.trim_end = parse_field(&self.trim_end, "end")?;
+        if let (Some(start), Some(end)) = (job.trim_start, job.trim_end)
+            && start >= end
+        {
+            return Err(tr("Start time must be before end time"));
+        }
+        Ok(())
+    }
+}
+
+/// A file in the batch queue
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueueItem {
+    /// Stable identifier, so items can be found again after the list changes
+    #[serde(skip, default = "next_id")]
+    pub id: u64,
+    pub input: PathBuf,
+    /// Subfolder of a dropped folder the input was found in, recreated in the output folder
+    pub relative_dir: PathBuf,
+    pub state: ItemState,
+    /// Position in the playlist the item came from, counting from 1, and the playlist length
+    pub playlist_position: Option<(usize, usize)>,
+    pub overrides: Overrides,
+    /// Converted before every waiting item that isn't
+    pub high_priority: bool,
+    /// Encode progress while converting, 0.0 to 1.0
+    #[serde(skip)]
+    pub progress: f32,
+    /// Files this item's conversion writes, claimed so parallel workers never pick the same ones
+    #[serde(skip)]
+    outputs: Vec<PathBuf>,
+}
+
+impl QueueItem {
+    pub fn new(input: PathBuf) -> Self {
+        Self {
+            id: next_id(),
+            input,
+            relative_dir: PathBuf::new(),
+            state: ItemState::Waiting,
+            playlist_position: None,
+            overrides: Overrides::default(),
+            high_priority: false,
+            progress: 0.0,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Item for entry `position` of a playlist with `len` entries
+    pub fn in_playlist(input: PathBuf, position: usize, len: usize) -> Self {
+        Self {
+            playlist_position: Some((position, len)),
+            ..Self::new(input)
+        }
+    }
+
+    /// Item for a file found while scanning the folder `root`
+    pub fn in_folder(input: PathBuf, root: &Path) -> Self {
+        let relative_dir = input
+            .parent()
+            .and_then(|dir| dir.strip_prefix(root).ok())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Self {
+            relative_dir,
+            ..Self::new(input)
+        }
+    }
+}
+
+/// Output filename without extension for `item` at `index` in the queue, counting from 1
+pub fn output_name(item: &QueueItem, index: usize, settings: &Settings) -> String {
+    let name = item.overrides.output_name.trim();
+    if !name.is_empty() {
+        return file_name_safe(name);
+    }
+
+    let mut settings = settings.clone();
+    if let Some(bitrate) = item.overrides.bitrate {
+        settings.bitrate = bitrate;
+        settings.auto_bitrate = false;
+    }
+    let name = resolve_template(&settings.batch_template, index, &item.input, &settings);
+    match item.playlist_position {
+        Some((position, len)) if settings.number_playlist => {
+            let width = len.to_string().len().max(2);
+            format!("{:0width$} - {}", position, name)
+        }
+        _ => name,
+    }
+}
+
+/// Fill in the batch name template for `input` at `index`
+///
+/// Placeholders are `{stem}`, `{index}`, `{bitrate}` and `{format}`; numbers take a width like
+/// `{index:02}`. An output extension at the end of the template is dropped, the format decides it.
+/// Characters that can't be in a file name become `_`, so the output stays in its folder.
+fn resolve_template(template: &str, index: usize, input: &Path, settings: &Settings) -> String {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let bitrate = if settings.auto_bitrate {
+        "auto".to_string()
+    } else {
+        settings.average_bitrate().to_string()
+    };
+
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        name.push_str(&rest[..start]);
+        let placeholder = &rest[start + 1..start + len];
+        let (field, width) = match placeholder.split_once(':') {
+            Some((field, width)) => (field, width.parse().unwrap_or(0)),
+            None => (placeholder, 0),
+        };
+        match field {
+            "stem" => name.push_str(&stem),
+            "index" => name.push_str(&format!("{:0width$}", index)),
+            "bitrate" => name.push_str(&format!("{:0>width$}", bitrate)),
+            "format" => name.push_str(settings.format.extension()),
+            // Unknown placeholders stay visible so typos show up in the preview
+            _ => name.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    name.push_str(rest);
+
+    let extension = Path::new(&name).extension().map(|e| e.to_string_lossy().to_lowercase());
+    if let Some(extension) = extension
+        && OutputFormat::ALL.iter().any(|f| f.extension() == extension)
+    {
+        name.truncate(name.len() - extension.len() - 1);
+    }
+    file_name_safe(&name)
+}
+
+/// `name` with the characters Windows or Unix reject in file names replaced by `_`
+fn file_name_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Every file below `root` accepted by `is_supported`, in sorted order
+pub fn scan_folder(root: &Path, is_supported: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(root) else {
+        println!("Failed to read folder '{}'", root.display());
+        return files;
+    };
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(scan_folder(&path, is_supported));
+        } else if is_supported(&path) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Whether `path` is already waiting or converting, as the same file or an identical copy
+///
+/// Copies are recognized by size and a hash of their first megabyte, which is enough
+/// to tell videos apart without reading them whole.
+pub fn is_duplicate(items: &[QueueItem], path: &Path) -> bool {
+    let canonical = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let target = canonical(path);
+    let size = |path: &Path| std::fs::metadata(path).map(|m| m.len()).ok();
+    let target_size = size(path);
+    let mut target_hash = None;
+
+    items
+        .iter()
+        .filter(|item| matches!(item.state, ItemState::Waiting | ItemState::Converting))
+        .any(|item| {
+            if canonical(&item.input) == target {
+                return true;
+            }
+            if target_size.is_none() || size(&item.input) != target_size {
+                return false;
+            }
+            let target_hash = *target_hash.get_or_insert_with(|| head_hash(path));
+            target_hash.is_some() && head_hash(&item.input) == target_hash
+        })
+}
+
+/// Hash of the first megabyte of a file
+fn head_hash(path: &Path) -> Option<u64> {
+    use std::hash::{DefaultHasher, Hasher};
+    use std::io::Read;
+
+    let mut head = Vec::new();
+    std::fs::File::open(path).ok()?.take(1_000_000).read_to_end(&mut head).ok()?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&head);
+    Some(hasher.finish())
+}
+
+/// Next time the clock shows `text`, e.g. `02:00`, today or tomorrow
+pub fn schedule_time(text: &str) -> Option<DateTime<Local>> {
+    let time = NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()?;
+    let now = Local::now();
+    let today = now.date_naive().and_time(time).and_local_timezone(Local).earliest()?;
+    if today > now {
+        Some(today)
+    } else {
+        (now.date_naive() + Days::new(1)).and_time(time).and_local_timezone(Local).earliest()
+    }
+}
+
+/// Whether any item is being converted right now
+pub fn is_running(queue: &Queue) -> bool {
+    queue
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|item| matches!(item.state, ItemState::Converting))
+}
+
+/// Convert waiting items until none are left or `paused` is set, as one of possibly several workers
+///
+/// Each worker claims the next waiting item and its output paths under the queue lock, so two
+/// workers never convert the same file or write the same output. Every item uses `settings` and
+/// `job`, with tags filled from its filename. Outputs go next to their inputs, or below `output_dir`
+/// in the same folder layout they were found in. Pausing lets the current item finish and leaves
+/// the rest waiting.
+pub async fn run(
+    queue: Queue,
+    paused: Arc<AtomicBool>,
+    settings: Settings,
+    job: JobOptions,
+    output_dir: Option<PathBuf>,
+) {
+    let mut finished: Option<(u64, ItemState)> = None;
+    loop {
+        // Recording a result and claiming the next item happen under one lock,
+        // so the queue never looks idle between two files
+        let next = {
+            let mut items = queue.lock().unwrap();
+            if let Some((id, state)) = finished.take()
+                && let Some(item) = items.iter_mut().find(|item| item.id == id)
+            {
+                item.state = state;
+                item.outputs.clear();
+            }
+            if paused.load(Ordering::Relaxed) {
+                save(&items, output_dir.as_deref());
+                break;
+            }
+            let waiting = |item: &QueueItem| matches!(item.state, ItemState::Waiting);
+            // High priority items jump ahead, the rest go in list order
+            let next = items
+                .iter()
+                .position(|item| waiting(item) && item.high_priority)
+                .or_else(|| items.iter().position(waiting))
+                .map(|index| {
+                    items[index].state = ItemState::Converting;
+                    (index + 1, items[index].clone())
+                });
+            save(&items, output_dir.as_deref());
+            next
+        };
+
+        let Some((index, item)) = next else {
+            break;
+        };
+        let mut settings = settings.clone();
+        if let Some(preset) = settings.preset_for(&item.input) {
+            preset.apply(&mut settings);
+        }
+        let mut job = job.clone();
+        if let Err(e) = item.overrides.apply(&mut settings, &mut job) {
+            finished = Some((item.id, ItemState::Failed(e)));
+            continue;
+        }
+
+        let mut output = match output_dir {
+            Some(ref dir) => {
+                let dir = dir.join(&item.relative_dir);
+                if let Err(e) = std::fs::create_dir_all(&dir) {
+                    finished = Some((item.id, ItemState::Failed(e.to_string())));
+                    continue;
+                }
+                dir.join(item.input.file_name().unwrap_or_default())
+            }
+            None => item.input.clone(),
+        };
+        let name = output_name(&item, index, &settings);
+        if !name.trim().is_empty() {
+            // The placeholder extension is swapped for the format's, so dots in the name survive
+            output.set_file_name(format!("{}.tmp", name));
+        }
+        let Some(output) = claim_output(&queue, &item, output, &settings) else {
+            println!("Skipped '{}', the output already exists", item.input.display());
+            finished = Some((item.id, ItemState::Skipped));
+            continue;
+        };
+        let on_progress = |progress: Progress| {
+            if let Some(entry) = queue.lock().unwrap().iter_mut().find(|entry| entry.id == item.id) {
+                entry.progress = progress.fraction;
+            }
+        };
+        let started = SystemTime::now();
+        // Cancelling removes the item, dropping the conversion then kills FFmpeg
+        let removed = async {
+            while queue.lock().unwrap().iter().any(|entry| entry.id == item.id) {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        };
+        let conversion = pipeline::STALL_TIMEOUT.scope(
+            settings.stall_timeout,
+            convert(&item.input, &output, &settings, job, &on_progress),
+        );
+        tokio::select! {
+            state = ffmpeg::LOW_PRIORITY.scope(settings.low_priority || settings.low_impact, conversion) => {
+                finished = Some((item.id, state));
+            }
+            _ = removed => {
+                println!("Cancelled '{}'", item.input.display());
+                pipeline::remove_outputs(&item.input, &output, &settings, None, started);
+            }
+        }
+    }
+}
+
+/// Pick where `item` is converted to and claim it, `None` when the existing output is to be kept
+///
+/// Besides finished files, a path is taken while another worker writes it, e.g. for `a.mkv`
+/// and `a.mp4` both becoming `a.mp3`. Those are always numbered, overwriting a file that is
+/// still being written would lose both. A `.part` file nobody claimed is left from a crash
+/// and removed, so a resumed item gets its original name back.
+fn claim_output(queue: &Queue, item: &QueueItem, output: PathBuf, settings: &Settings) -> Option<PathBuf> {
+    let mut items = queue.lock().unwrap();
+    let in_flight: Vec<&PathBuf> = items.iter().flat_map(|other| &other.outputs).collect();
+    let busy = |candidate: &Path| {
+        pipeline::output_paths(&item.input, candidate, settings)
+            .iter()
+            .any(|path| in_flight.contains(&path))
+    };
+    let exists = |candidate: &Path| !pipeline::existing_outputs(&item.input, candidate, settings).is_empty();
+
+    let output = match settings.existing_output {
+        ExistingOutput::Skip if exists(&output) => return None,
+        ExistingOutput::Overwrite if !busy(&output) => output,
+        // Nobody is asked mid-batch, keeping both files loses nothing
+        _ if busy(&output) || exists(&output) => pipeline::numbered(&output, |candidate| !busy(candidate) && !exists(candidate)),
+        _ => output,
+    };
+    let outputs = pipeline::output_paths(&item.input, &output, settings);
+    for path in &outputs {
+        let part = pipeline::part_path(path);
+        if part.exists()
+            && let Err(e) = std::fs::remove_file(&part)
+        {
+            println!("Failed to remove '{}': {}", part.display(), e);
+        }
+    }
+    if let Some(entry) = items.iter_mut().find(|entry| entry.id == item.id) {
+        entry.outputs = outputs;
+    }
+    Some(output)
+}
+
+/// Put failed items back to waiting, returning how many there were
+pub fn retry_failed(queue: &Queue) -> usize {
+    let mut retried = 0;
+    for item in queue.lock().unwrap().iter_mut() {
+        if let ItemState::Failed(_) = item.state {
+            item.state = ItemState::Waiting;
+            item.progress = 0.0;
+            retried += 1;
+        }
+    }
+    retried
+}
+
+/// Move item `id` to `index`, the items from there on shift down by one
+pub fn move_item(queue: &Queue, id: u64, index: usize) {
+    let mut items = queue.lock().unwrap();
+    if let Some(from) = items.iter().position(|item| item.id == id) {
+        let item = items.remove(from);
+        let index = index.min(items.len());
+        items.insert(index, item);
+    }
+}
+
+/// Take an item out of the queue, stopping its conversion if it is running
+pub fn cancel(queue: &Queue, id: u64) {
+    queue.lock().unwrap().retain(|item| item.id != id);
+}
+
+/// Probe and convert a single queued file, `output` gets the format's extension
+async fn convert(
+    input: &Path,
+    output: &Path,
+    settings: &Settings,
+    mut job: JobOptions,
+    on_progress: &ProgressFn<'_>,
+) -> ItemState {
+    let media = match probe::probe(input).await {
+        Ok(media) => Some(media),
+        Err(e) => {
+            println!("Failed to probe '{}': {}", input.display(), e);
+            None
+        }
+    };
+
+    let mut settings = settings.clone();
+    if settings.auto_bitrate
+        && let Some(stream) = media.as_ref().and_then(|m| m.audio_stream(None))
+    {
+        settings.bitrate = settings::auto_bitrate(stream);
+    }
+
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    if let Some(tags) = Tags::from_filename(&stem, &settings.filename_pattern) {
+        job.tags = tags;
+    }
+
+    let output = pipeline::output_path(input, output, settings.format);
+    let result = converter::for_settings(&settings)
+        .convert(input, &output, &settings, job, media.as_ref(), on_progress)
+        .await;
+    if let Ok(conversion) = &result {
+        if settings.preserve_timestamps {
+            pipeline::copy_timestamps(input, &conversion.outputs);
+        }
+        pipeline::dispose_source(input, conversion, settings.source_action);
+    }
+    match result {
+        Ok(Conversion { outputs, warning: None }) => ItemState::Done(outputs),
+        Ok(Conversion { outputs, warning: Some(warning) }) => ItemState::Suspicious(outputs, warning),
+        Err(e) => ItemState::Failed(e),
+    }
+}
+
+/// Unfinished batch kept on disk, so it survives the app being killed
+#[derive(Serialize, Deserialize)]
+pub struct SavedQueue {
+    pub items: Vec<QueueItem>,
+    pub output_dir: Option<PathBuf>,
+}
+
+fn saved_queue_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "vid2mp3").map(|dirs| dirs.data_dir().join("queue.json"))
+}
+
+/// Remember `items` for resuming, or forget the saved batch once nothing is left to convert
+pub fn save(items: &[QueueItem], output_dir: Option<&Path>) {
+    let Some(path) = saved_queue_path() else {
+        return;
+    };
+    if !items.iter().any(|item| matches!(item.state, ItemState::Waiting | ItemState::Converting)) {
+        discard_saved();
+        return;
+    }
+
+    if let Some(parent) = path.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        println!("Failed to create data dir: {}", e);
+        return;
+    }
+    let saved = SavedQueue {
+        items: items.to_vec(),
+        output_dir: output_dir.map(Path::to_path_buf),
+    };
+    match serde_json::to_string_pretty(&saved) {
+        Ok(contents) => {
+            if let Err(e) = std::fs::write(&path, contents) {
+                println!("Failed to save queue: {}", e);
+            }
+        }
+        Err(e) => println!("Failed to serialize queue: {}", e),
+    }
+}
+
+/// A batch that was still running or waiting when the app last quit
+///
+/// Items that were converting are waiting again, their outputs are incomplete.
+pub fn load_interrupted() -> Option<SavedQueue> {
+    let path = saved_queue_path()?;
+    let contents = std::fs::read_to_string(&path).ok()?;
+    let mut saved: SavedQueue = match serde_json::from_str(&contents) {
+        Ok(saved) => saved,
+        Err(e) => {
+            println!("Failed to parse saved queue '{}': {}", path.display(), e);
+            return None;
+        }
+    };
+    for item in &mut saved.items {
+        if let ItemState::Converting = item.state {
+            item.state = ItemState::Waiting;
+        }
+    }
+    saved
+        .items
+        .iter()
+        .any(|item| matches!(item.state, ItemState::Waiting))
+        .then_some(saved)
+}
+
+/// Forget the saved batch
+pub fn discard_saved() {
+    if let Some(path) = saved_queue_path()
+        && path.exists()
+        && let Err(e) = std::fs::remove_file(&path)
+    {
+        println!("Failed to remove saved queue: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(template: &str) -> String {
+        resolve_template(template, 7, Path::new("videos/Holiday Clip.mkv"), &Settings::default())
+    }
+
+    #[test]
+    fn template_fills_placeholders() {
+        assert_eq!(resolve("{stem}"), "Holiday Clip");
+        assert_eq!(resolve("{index:02} - {stem}"), "07 - Holiday Clip");
+        assert_eq!(resolve("{index:03}"), "007");
+        assert_eq!(resolve("{stem} ({bitrate} kbps)"), "Holiday Clip (192 kbps)");
+        assert_eq!(resolve("{bitrate:04}"), "0192");
+        assert_eq!(resolve("{stem}.{format}"), "Holiday Clip");
+    }
+
+    #[test]
+    fn template_keeps_unknown_placeholders_and_unbalanced_braces() {
+        assert_eq!(resolve("{title} - {stem}"), "{title} - Holiday Clip");
+        assert_eq!(resolve("{stem} {index"), "Holiday Clip {index");
+        assert_eq!(resolve("stem} {index}"), "stem} 7");
+    }
+
+    #[test]
+    fn template_replaces_characters_illegal_in_file_names() {
+        assert_eq!(resolve("{stem}/../{index}"), "Holiday Clip_.._7");
+        assert_eq!(resolve(r#"a\b:c*d?e"f<g>h|i"#), "a_b_c_d_e_f_g_h_i");
+    }
+
+    #[test]
+    fn output_name_prefers_override_and_numbers_playlists() {
+        let settings = Settings {
+            batch_template: "{index:02} {stem}".to_string(),
+            number_playlist: true,
+            ..Settings::default()
+        };
+        let item = QueueItem::in_playlist(PathBuf::from("clip.mp4"), 3, 120);
+        assert_eq!(output_name(&item, 1, &settings), "003 - 01 clip");
+
+        let mut item = QueueItem::new(PathBuf::from("clip.mp4"));
+        item.overrides.output_name = " Side A: Intro ".to_string();
+        assert_eq!(output_name(&item, 1, &settings), "Side A_ Intro");
+    }
+
+    fn write(dir: &Path, name: &str, contents: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn same_path_is_duplicate_however_it_is_spelled() {
+        // Inside the working directory, so the file also has a relative spelling
+        let cwd = std::env::current_dir().unwrap();
+        let dir = tempfile::tempdir_in(&cwd).unwrap();
+        let path = write(dir.path(), "clip.mp4", b"video");
+        let relative = path.strip_prefix(&cwd).unwrap().to_path_buf();
+
+        let items = [QueueItem::new(path.clone())];
+        assert!(is_duplicate(&items, &path));
+        assert!(is_duplicate(&items, &relative));
+        assert!(is_duplicate(&items, &dir.path().join(".").join("clip.mp4")));
+        assert!(is_duplicate(&[QueueItem::new(relative)], &path));
+    }
+
+    #[test]
+    fn identical_copy_is_duplicate() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = write(dir.path(), "clip.mp4", b"the same video");
+        let copy = write(dir.path(), "clip (1).mp4", b"the same video");
+        assert!(is_duplicate(&[QueueItem::new(original)], &copy));
+    }
+
+    #[test]
+    fn same_size_with_other_contents_is_not_duplicate() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = write(dir.path(), "first.mp4", b"video one");
+        let second = write(dir.path(), "second.mp4", b"video two");
+        assert!(!is_duplicate(&[QueueItem::new(first)], &second));
+    }
+
+    #[test]
+    fn finished_items_are_not_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "clip.mp4", b"video");
+        let mut item = QueueItem::new(path.clone());
+        item.state = ItemState::Done(Vec::new());
+        assert!(!is_duplicate(&[item], &path));
+    }
+}
@@ -0,0 +1,614 @@
+use crate::preset::Preset;
+use crate::probe::StreamInfo;
+use crate::profile::Profile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bitrates offered in the bitrate dropdown (kbps)
+pub const BITRATES: [u32; 7] = [64, 96, 128, 160, 192, 256, 320];
+
+/// Smallest offered bitrate that keeps everything a `source_kbps` stream contains
+pub fn matching_bitrate(source_kbps: u32) -> u32 {
+    BITRATES
+        .iter()
+        .copied()
+        .find(|&b| b >= source_kbps)
+        .unwrap_or(BITRATES[BITRATES.len() - 1])
+}
+
+/// Bitrate used by "Auto" when the source doesn't report one
+const AUTO_FALLBACK_BITRATE: u32 = 192;
+
+/// MP3 bitrate that preserves what `stream` holds without wasting space
+///
+/// Lossless sources get the best quality, lossy ones roughly their own bitrate, with
+/// headroom for codecs that are more efficient than MP3.
+pub fn auto_bitrate(stream: &StreamInfo) -> u32 {
+    let channels = stream.channels.unwrap_or(2).max(1);
+    if stream.is_lossless() {
+        return if channels == 1 { 160 } else { 320 };
+    }
+    let Some(kbps) = stream.bitrate_kbps() else {
+        return AUTO_FALLBACK_BITRATE;
+    };
+
+    let efficiency = match stream.codec_name.as_str() {
+        "aac" | "opus" | "vorbis" => 1.25,
+        _ => 1.0,
+    };
+    // Surround tracks end up as stereo, so only two channels' worth of bits carry over
+    let kept_channels = channels.min(2);
+    let target = kbps as f64 * efficiency * kept_channels as f64 / channels as f64;
+    matching_bitrate(target.round() as u32)
+}
+
+/// Output sample rates offered in the advanced options (Hz)
+pub const SAMPLE_RATES: [u32; 3] = [22050, 44100, 48000];
+
+/// Length of a ringtone clip in seconds
+pub const RINGTONE_LENGTH: f64 = 30.0;
+
+/// Container and codec of the converted file
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OutputFormat {
+    Mp3,
+    /// AAC audiobook with chapters
+    M4b,
+    /// Lossless FLAC
+    Flac,
+    /// AAC in an iPhone ringtone container
+    M4r,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [OutputFormat::Mp3, OutputFormat::M4b, OutputFormat::Flac, OutputFormat::M4r];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::M4b => "m4b",
+            OutputFormat::Flac => "flac",
+            OutputFormat::M4r => "m4r",
+        }
+    }
+
+    /// FFmpeg audio encoder writing this format
+    pub fn encoder(self) -> &'static str {
+        match self {
+            OutputFormat::Mp3 => "libmp3lame",
+            OutputFormat::Flac => "flac",
+            OutputFormat::M4b | OutputFormat::M4r => "aac",
+        }
+    }
+
+    /// Codec of the audio this format holds, as ffprobe names it
+    pub fn codec(self) -> &'static str {
+        match self {
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::Flac => "flac",
+            OutputFormat::M4b | OutputFormat::M4r => "aac",
+        }
+    }
+
+    /// FFmpeg muxer name, always passed since `.part` files don't tell FFmpeg the format
+    pub fn muxer(self) -> &'static str {
+        match self {
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::Flac => "flac",
+            OutputFormat::M4b | OutputFormat::M4r => "ipod",
+        }
+    }
+}
+
+/// MP3 rate control mode
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EncodeMode {
+    /// Constant bitrate, uses `bitrate`
+    Cbr,
+    /// Variable bitrate, uses LAME's `vbr_quality` scale
+    Vbr,
+}
+
+/// Output channel layout
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Channels {
+    /// Keep the source channel layout
+    Keep,
+    Stereo,
+    Mono,
+}
+
+/// How 5.1 and 7.1 sources are folded down to stereo
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Downmix {
+    /// FFmpeg's standard downmix
+    Default,
+    /// Matrix-encoded stereo that surround decoders can unfold again
+    ProLogic2,
+    /// Louder center channel, so dialogue isn't buried under effects
+    DialogueBoost,
+}
+
+/// How the output is split into multiple files
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SplitMode {
+    /// Single output file
+    None,
+    /// Split at gaps of silence
+    Silence,
+    /// Split into parts of `segment_minutes` each
+    Fixed,
+}
+
+/// What to do when a conversion would replace an existing file
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExistingOutput {
+    /// Ask every time
+    Ask,
+    Overwrite,
+    /// Number the new file, e.g. `song (1).mp3`
+    Rename,
+    Skip,
+}
+
+impl ExistingOutput {
+    pub const ALL: [ExistingOutput; 4] = [
+        ExistingOutput::Ask,
+        ExistingOutput::Overwrite,
+        ExistingOutput::Rename,
+        ExistingOutput::Skip,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExistingOutput::Ask => "Ask",
+            ExistingOutput::Overwrite => "Overwrite",
+            ExistingOutput::Rename => "Rename new file",
+            ExistingOutput::Skip => "Skip",
+        }
+    }
+}
+
+/// What happens to the source file once it's converted
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SourceAction {
+    Keep,
+    /// Move it into a `converted` folder next to it
+    MoveToConverted,
+    /// Send it to the recycle bin
+    Trash,
+}
+
+impl SourceAction {
+    pub const ALL: [SourceAction; 3] = [SourceAction::Keep, SourceAction::MoveToConverted, SourceAction::Trash];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SourceAction::Keep => "Keep",
+            SourceAction::MoveToConverted => "Move to \"converted\"",
+            SourceAction::Trash => "Move to recycle bin",
+        }
+    }
+}
+
+/// Colour scheme of the window
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    /// Light or dark as the operating system is set
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+
+    pub fn preference(self) -> eframe::egui::ThemePreference {
+        match self {
+            Theme::System => eframe::egui::ThemePreference::System,
+            Theme::Light => eframe::egui::ThemePreference::Light,
+            Theme::Dark => eframe::egui::ThemePreference::Dark,
+        }
+    }
+}
+
+/// Version of the settings file layout, bump it with a new entry in `MIGRATIONS`
+const SETTINGS_VERSION: u32 = 1;
+
+/// Upgrades of the raw settings file, entry `n` takes a file from version `n` to `n + 1`
+///
+/// Fields that are only added need no entry, missing fields get their defaults. Renamed or
+/// reshaped fields do, or the user's choice is lost.
+const MIGRATIONS: [fn(&mut serde_json::Value); SETTINGS_VERSION as usize] = [
+    // Files from before versioning have the current layout
+    |_| {},
+];
+
+/// User settings persisted between sessions
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Layout version of the file these were read from, see `SETTINGS_VERSION`
+    pub version: u32,
+    pub format: OutputFormat,
+    /// Formats written in addition to `format` from the same input
+    pub extra_formats: Vec<OutputFormat>,
+    pub mode: EncodeMode,
+    pub bitrate: u32,
+    /// Pick the CBR bitrate from the source audio instead of using `bitrate`
+    pub auto_bitrate: bool,
+    /// LAME VBR quality, 0 (best) to 9 (smallest)
+    pub vbr_quality: u8,
+    /// Output sample rate in Hz, `None` keeps the source rate
+    pub sample_rate: Option<u32>,
+    pub channels: Channels,
+    /// Downmix used when the source has more than two channels
+    pub downmix: Downmix,
+    /// Copy the audio stream as-is when the source already has the output format's codec
+    pub stream_copy: bool,
+    /// Run two-pass `loudnorm` normalization
+    pub normalize: bool,
+    /// Integrated loudness target for normalization (LUFS)
+    pub loudness_target: f32,
+    /// Fade-in duration in seconds, 0 disables it
+    pub fade_in: f32,
+    /// Fade-out duration in seconds, 0 disables it
+    pub fade_out: f32,
+    /// Playback speed multiplier, 0.5 to 3.0
+    pub speed: f32,
+    /// Keep the original pitch when changing speed
+    pub preserve_pitch: bool,
+    pub split: SplitMode,
+    /// Minimum silence length that separates two tracks, in seconds
+    pub silence_min_duration: f32,
+    /// Length of each part when splitting into fixed-length segments
+    pub segment_minutes: u32,
+    /// Embed the video thumbnail as album art
+    pub embed_cover: bool,
+    /// Pattern used to pre-fill tags from the input filename, e.g. `%artist% - %title%`
+    pub filename_pattern: String,
+    /// Copy metadata such as title and artist from the source container
+    pub copy_metadata: bool,
+    /// Write a .cue sheet with chapter or silence track marks when not splitting
+    pub write_cue: bool,
+    /// Render a spectrogram PNG next to the output
+    pub spectrogram: bool,
+    /// Also export text subtitles as .srt next to the output
+    pub export_subtitles: bool,
+    /// Analyze the output and write ReplayGain tags
+    pub replaygain: bool,
+    /// Output format used in ringtone mode
+    pub ringtone_format: OutputFormat,
+    /// Chapter length for audiobooks whose source has no chapters, 0 disables them
+    pub chapter_minutes: u32,
+    /// Extra FFmpeg arguments added before the output, shell-style quoting allowed
+    pub extra_args: String,
+    /// How many queue items are converted at the same time
+    pub parallel_jobs: usize,
+    /// Run batch conversions at reduced priority with fewer threads so the machine stays responsive
+    pub low_impact: bool,
+    /// FFmpeg threads per batch conversion in low impact mode, 0 lets FFmpeg decide
+    pub low_impact_threads: usize,
+    /// Prefix outputs of playlist entries with their position, e.g. `03 - `
+    pub number_playlist: bool,
+    /// Collapse converted queue items into a summary row
+    pub hide_finished: bool,
+    /// Output name for batch items, with placeholders like `{stem}` and `{index:02}`
+    pub batch_template: String,
+    /// Presets saved by the user, shown after the built-in ones
+    pub custom_presets: Vec<Preset>,
+    /// Preset names applied to batch items by input extension, lowercase without the dot
+    pub extension_presets: BTreeMap<String, String>,
+    /// Workflows switched between from the top of the window
+    pub profiles: Vec<Profile>,
+    /// Profile the current settings belong to, they are stored back into it when switching away
+    pub active_profile: Option<String>,
+    /// What converting does when the output file is already there
+    pub existing_output: ExistingOutput,
+    /// FFmpeg binary to run, empty finds it on PATH
+    pub ffmpeg_path: String,
+    /// ffprobe binary to run, empty uses the one next to `ffmpeg_path` or finds it on PATH
+    pub ffprobe_path: String,
+    /// Convert with the FFmpeg libraries built into the app instead of running ffmpeg, needs the `native` feature
+    pub native_backend: bool,
+    /// Decode the source on the GPU with `-hwaccel auto`, FFmpeg falls back to the CPU when it can't
+    pub hardware_decode: bool,
+    /// Seconds FFmpeg may run without progress before it's treated as hung and stopped, 0 never stops it
+    pub stall_timeout: u32,
+    /// Run FFmpeg below normal priority for conversions and thumbnails
+    pub low_priority: bool,
+    /// Folder converted files are written to, `None` puts them next to the source
+    pub output_dir: Option<PathBuf>,
+    /// What happens to the source after a conversion whose output checks out
+    pub source_action: SourceAction,
+    /// Give outputs the source's modified and, where the system allows, created time
+    pub preserve_timestamps: bool,
+    /// Show desktop notifications for finished work while the window is in the background
+    pub notifications: bool,
+    /// Look for a newer release on GitHub at startup
+    pub check_updates: bool,
+    pub theme: Theme,
+    /// Language code of the interface, `en` or a catalog's name
+    pub language: String,
+    /// Inner size of the window when it was last closed
+    pub window_size: Option<[f32; 2]>,
+    /// Top-left corner of the window when it was last closed, `None` lets the system place it
+    pub window_position: Option<[f32; 2]>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            format: OutputFormat::Mp3,
+            extra_formats: Vec::new(),
+            mode: EncodeMode::Cbr,
+            bitrate: 192,
+            auto_bitrate: false,
+            vbr_quality: 2,
+            sample_rate: None,
+            channels: Channels::Keep,
+            downmix: Downmix::Default,
+            stream_copy: false,
+            normalize: false,
+            loudness_target: -16.0,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            speed: 1.0,
+            preserve_pitch: true,
+            split: SplitMode::None,
+            silence_min_duration: 2.0,
+            segment_minutes: 30,
+            embed_cover: false,
+            filename_pattern: "%artist% - %title%".to_string(),
+            copy_metadata: true,
+            write_cue: false,
+            spectrogram: false,
+            export_subtitles: false,
+            replaygain: false,
+            ringtone_format: OutputFormat::Mp3,
+            chapter_minutes: 10,
+            extra_args: String::new(),
+            parallel_jobs: default_parallel_jobs(),
+            low_impact: false,
+            low_impact_threads: 2,
+            number_playlist: false,
+            hide_finished: false,
+            batch_template: "{stem}".to_string(),
+            custom_presets: Vec::new(),
+            extension_presets: BTreeMap::new(),
+            profiles: Vec::new(),
+            active_profile: None,
+            existing_output: ExistingOutput::Ask,
+            ffmpeg_path: String::new(),
+            ffprobe_path: String::new(),
+            native_backend: false,
+            hardware_decode: false,
+            stall_timeout: 60,
+            low_priority: true,
+            output_dir: None,
+            source_action: SourceAction::Keep,
+            preserve_timestamps: false,
+            notifications: true,
+            check_updates: false,
+            theme: Theme::Dark,
+            language: "en".to_string(),
+            window_size: None,
+            window_position: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Phone-friendly variant of these settings used in ringtone mode
+    pub fn ringtone(&self) -> Self {
+        Self {
+            format: self.ringtone_format,
+            extra_formats: Vec::new(),
+            mode: EncodeMode::Cbr,
+            bitrate: 128,
+            auto_bitrate: false,
+            sample_rate: Some(44100),
+            channels: Channels::Stereo,
+            stream_copy: false,
+            fade_in: 0.0,
+            fade_out: 1.0,
+            speed: 1.0,
+            split: SplitMode::None,
+            replaygain: false,
+            ..self.clone()
+        }
+    }
+
+    /// `format` followed by the extra formats, without duplicates
+    pub fn output_formats(&self) -> Vec<OutputFormat> {
+        let mut formats = vec![self.format];
+        for &format in &self.extra_formats {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        formats
+    }
+
+    /// Expected average audio bitrate in kbps, used for size estimates
+    pub fn average_bitrate(&self) -> u32 {
+        match (self.format, self.mode) {
+            // Typical LAME VBR averages for quality 0 to 9
+            (OutputFormat::Mp3, EncodeMode::Vbr) => [245, 225, 190, 175, 165, 130, 115, 100, 85, 65]
+                .get(self.vbr_quality as usize)
+                .copied()
+                .unwrap_or(130),
+            // Roughly what FLAC gets out of CD-quality stereo
+            (OutputFormat::Flac, _) => 900,
+            _ => self.bitrate,
+        }
+    }
+
+    /// Built-in presets followed by the user's own
+    pub fn presets(&self) -> Vec<Preset> {
+        let mut presets = Preset::built_in();
+        presets.extend(self.custom_presets.iter().cloned());
+        presets
+    }
+
+    /// Preset mapped to the extension of `input`, if there is one and it still exists
+    pub fn preset_for(&self, input: &Path) -> Option<Preset> {
+        let extension = input.extension()?.to_string_lossy().to_lowercase();
+        let name = self.extension_presets.get(&extension)?;
+        // Saved presets shadow built-in ones of the same name
+        self.presets().into_iter().rev().find(|preset| &preset.name == name)
+    }
+
+    /// Load settings from the config file, falling back to defaults
+    pub fn load() -> Self {
+        let Some(path) = settings_path() else {
+            return Self::default();
+        };
+
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        // Files this version can't fully understand are kept, the next change saves over them
+        let backup = || {
+            if let Err(e) = fs::copy(&path, path.with_extension("json.bak")) {
+                println!("Failed to back up settings: {}", e);
+            }
+        };
+        match Self::parse(&contents) {
+            Ok(mut settings) => {
+                if settings.version > SETTINGS_VERSION {
+                    println!("Settings were written by a newer version, unknown options are dropped");
+                    backup();
+                    settings.version = SETTINGS_VERSION;
+                }
+                settings
+            }
+            Err(e) => {
+                println!("Failed to parse settings '{}': {}", path.display(), e);
+                backup();
+                Self::default()
+            }
+        }
+    }
+
+    /// Read a settings file of an older version, migrating it to the current layout
+    ///
+    /// A newer file keeps its `version`, so the caller can tell.
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut value: serde_json::Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        for migration in MIGRATIONS.iter().skip(version) {
+            migration(&mut value);
+        }
+        let mut settings: Self = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        settings.version = settings.version.max(SETTINGS_VERSION);
+        Ok(settings)
+    }
+
+    /// Defaults for everything except what the user made: presets, profiles and their file type mapping,
+    /// and the language so the window stays readable
+    pub fn reset(&self) -> Self {
+        Self {
+            language: self.language.clone(),
+            custom_presets: self.custom_presets.clone(),
+            extension_presets: self.extension_presets.clone(),
+            profiles: self.profiles.clone(),
+            window_size: self.window_size,
+            window_position: self.window_position,
+            ..Self::default()
+        }
+    }
+
+    /// Read settings exported with `export`, presets included, migrating older exports
+    pub fn import(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut settings = Self::parse(&contents)?;
+        settings.version = SETTINGS_VERSION;
+        Ok(settings)
+    }
+
+    /// Write the whole configuration, presets included, to a file for another machine
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Write settings to the config file
+    pub fn save(&self) {
+        let Some(path) = settings_path() else {
+            return;
+        };
+
+        if let Some(parent) = path.parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            println!("Failed to create config dir: {}", e);
+            return;
+        }
+
+        match serde_json::to_string_pretty(self) {
+            Ok(contents) => {
+                if let Err(e) = fs::write(&path, contents) {
+                    println!("Failed to save settings: {}", e);
+                }
+            }
+            Err(e) => println!("Failed to serialize settings: {}", e),
+        }
+    }
+}
+
+/// Half the CPU cores, but no more than two FFmpeg processes at once
+fn default_parallel_jobs() -> usize {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    (cores / 2).clamp(1, 2)
+}
+
+fn settings_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "vid2mp3")
+        .map(|dirs| dirs.config_dir().join("settings.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_file_is_migrated_keeping_user_values() {
+        let settings = Settings::parse(r#"{"format": "Flac", "bitrate": 320, "speed": 1.5}"#).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(settings.format == OutputFormat::Flac);
+        assert_eq!(settings.bitrate, 320);
+        assert_eq!(settings.speed, 1.5);
+    }
+
+    #[test]
+    fn newer_file_still_parses_and_reports_its_version() {
+        let contents = format!(r#"{{"version": {}, "bitrate": 256}}"#, SETTINGS_VERSION + 1);
+        let settings = Settings::parse(&contents).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION + 1);
+        assert_eq!(settings.bitrate, 256);
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let settings = Settings::parse(r#"{"version": 1, "bitrate": 128, "no_such_option": [1, 2]}"#).unwrap();
+        assert_eq!(settings.bitrate, 128);
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        assert!(Settings::parse("{\"bitrate\": ").is_err());
+        assert!(Settings::parse(r#"{"bitrate": "loud"}"#).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+/// ID3 tags entered by the user, empty fields are left out
+#[derive(Clone, Default)]
+pub struct Tags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub genre: String,
+}
+
+/// Piece of a filename pattern
+enum Token {
+    Literal(String),
+    Field(String),
+}
+
+impl Tags {
+    /// `-metadata` arguments for every non-empty field
+    pub fn metadata_args(&self) -> Vec<String> {
+        let fields = [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("date", &self.year),
+            ("genre", &self.genre),
+        ];
+
+        let mut args = Vec::new();
+        for (key, value) in fields {
+            let value = value.trim();
+            if !value.is_empty() {
+                args.push("-metadata".to_string());
+                args.push(format!("{}={}", key, value));
+            }
+        }
+        args
+    }
+
+    /// Parse a filename like `Artist - Title` with a pattern like `%artist% - %title%`
+    ///
+    /// Supported fields are `%title%`, `%artist%`, `%album%`, `%year%` and `%genre%`;
+    /// any other `%name%` matches text that is ignored. Returns `None` if the
+    /// filename doesn't fit the pattern.
+    pub fn from_filename(stem: &str, pattern: &str) -> Option<Self> {
+        let tokens = tokenize(pattern);
+        let mut tags = Self::default();
+        let mut rest = stem;
+
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                Token::Field(name) => {
+                    // A field runs up to the next literal, or to the end of the name
+                    let value = match tokens.get(i + 1) {
+                        Some(Token::Literal(next)) => {
+                            let end = rest.find(next.as_str())?;
+                            let value = &rest[..end];
+                            rest = &rest[end..];
+                            value
+                        }
+                        _ => std::mem::take(&mut rest),
+                    };
+                    if let Some(field) = tags.field_mut(name) {
+                        *field = value.trim().to_string();
+                    }
+                }
+            }
+        }
+
+        rest.is_empty().then_some(tags)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut String> {
+        match name {
+            "title" => Some(&mut self.title),
+            "artist" => Some(&mut self.artist),
+            "album" => Some(&mut self.album),
+            "year" => Some(&mut self.year),
+            "genre" => Some(&mut self.genre),
+            _ => None,
+        }
+    }
+}
+
+/// Split a pattern into literals and `%field%` placeholders
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Literal(rest[..start].to_string()));
+        }
+        tokens.push(Token::Field(rest[start + 1..start + 1 + len].to_lowercase()));
+        rest = &rest[start + len + 2..];
+    }
+
+    if !rest.is_empty() {
+        tokens.push(Token::Literal(rest.to_string()));
+    }
+    tokens
+}
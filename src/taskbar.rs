@@ -0,0 +1,85 @@
+/// What the taskbar button shows
+#[derive(Clone, Copy, PartialEq)]
+pub enum TaskbarState {
+    Idle,
+    /// Green fill, 0.0 to 1.0
+    Progress(f32),
+    /// Red fill after a failed conversion
+    Error,
+}
+
+/// Conversion progress on the Windows taskbar button, does nothing elsewhere
+pub struct Taskbar {
+    state: TaskbarState,
+    #[cfg(target_os = "windows")]
+    list: Option<(windows::Win32::UI::Shell::ITaskbarList3, windows::Win32::Foundation::HWND)>,
+}
+
+impl Taskbar {
+    #[cfg(target_os = "windows")]
+    pub fn new(cc: &eframe::CreationContext) -> Self {
+        use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, CoCreateInstance, CoInitializeEx};
+        use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList};
+
+        let hwnd = match cc.window_handle().map(|handle| handle.as_raw()) {
+            Ok(RawWindowHandle::Win32(handle)) => Some(HWND(handle.hwnd.get() as *mut _)),
+            _ => None,
+        };
+        let list = hwnd.and_then(|hwnd| unsafe {
+            // The UI thread already uses COM through the window, this only makes sure it's set up
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            let list: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER).ok()?;
+            list.HrInit().ok()?;
+            Some((list, hwnd))
+        });
+        if list.is_none() {
+            println!("Taskbar progress is not available");
+        }
+        Self {
+            state: TaskbarState::Idle,
+            list,
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    pub fn new(_cc: &eframe::CreationContext) -> Self {
+        Self {
+            state: TaskbarState::Idle,
+        }
+    }
+
+    /// Show `state`, the taskbar is only touched when it changes
+    pub fn set(&mut self, state: TaskbarState) {
+        if state == self.state {
+            return;
+        }
+        self.state = state;
+        #[cfg(target_os = "windows")]
+        self.show();
+    }
+
+    #[cfg(target_os = "windows")]
+    fn show(&self) {
+        use windows::Win32::UI::Shell::{TBPF_ERROR, TBPF_NOPROGRESS, TBPF_NORMAL};
+
+        if let Some((ref list, hwnd)) = self.list {
+            // Progress values are fixed point, out of 1000
+            let result = unsafe {
+                match self.state {
+                    TaskbarState::Idle => list.SetProgressState(hwnd, TBPF_NOPROGRESS),
+                    TaskbarState::Progress(progress) => list
+                        .SetProgressState(hwnd, TBPF_NORMAL)
+                        .and_then(|_| list.SetProgressValue(hwnd, (progress * 1000.0) as u64, 1000)),
+                    TaskbarState::Error => list
+                        .SetProgressState(hwnd, TBPF_ERROR)
+                        .and_then(|_| list.SetProgressValue(hwnd, 1000, 1000)),
+                }
+            };
+            if let Err(e) = result {
+                println!("Failed to update taskbar progress: {}", e);
+            }
+        }
+    }
+}
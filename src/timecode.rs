@@ -0,0 +1,48 @@
+/// Parse a timestamp like `1:02:03.5`, `02:03` or `123.5` into seconds
+pub fn parse(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for part in text.split(':') {
+        let value: f64 = part.trim().parse().ok()?;
+        // `nan` and `inf` parse as numbers too, FFmpeg can't seek to them
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(seconds)
+}
+
+/// Format seconds as `hh:mm:ss`, or `mm:ss` for anything under an hour
+pub fn format(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (hours, minutes, secs) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse("1:02:03.5"), Some(3723.5));
+        assert_eq!(parse(" 02:03 "), Some(123.0));
+        assert_eq!(parse("123.5"), Some(123.5));
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_values() {
+        for text in ["", "-1", "1:-5", "nan", "NaN", "inf", "-inf", "1:infinity", "abc"] {
+            assert_eq!(parse(text), None, "{:?}", text);
+        }
+    }
+}
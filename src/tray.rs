@@ -0,0 +1,182 @@
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use crate::i18n::{tr, tr_args};
+use eframe::egui::{self, IconData};
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use std::sync::{Arc, Mutex};
+
+/// What the tray icon shows
+#[derive(Clone, Copy, PartialEq)]
+pub enum TrayState {
+    Idle,
+    /// Single conversion or queue running, 0.0 to 1.0
+    Converting(f32),
+    Done,
+}
+
+/// Something picked from the tray menu, handled by the app on its next frame
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub enum TrayCommand {
+    /// Pause a running queue or resume a paused one
+    TogglePause,
+    Quit,
+}
+
+/// Tray icon with a menu to show the window, pause the queue or quit
+///
+/// Only on Windows and macOS, Linux trays need a GTK main loop that eframe doesn't run.
+pub struct Tray {
+    /// Last shown state and pause menu entry
+    state: Option<(TrayState, Option<bool>)>,
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    icon: tray_icon::TrayIcon,
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    base: IconData,
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    pause: tray_icon::menu::MenuItem,
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    commands: Arc<Mutex<Vec<TrayCommand>>>,
+}
+
+impl Tray {
+    /// Add the icon to the tray, `None` when the platform or icon doesn't allow it
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    pub fn new(ctx: &egui::Context, icon: Option<&IconData>) -> Option<Self> {
+        use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+        use tray_icon::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+        let base = icon?.clone();
+        let show = MenuItem::with_id("show", tr("Show window"), true, None);
+        let pause = MenuItem::with_id("pause", tr("Pause queue"), false, None);
+        let quit = MenuItem::with_id("quit", tr("Quit"), true, None);
+        let menu = Menu::new();
+        if let Err(e) = menu.append_items(&[&show, &pause, &PredefinedMenuItem::separator(), &quit]) {
+            println!("Failed to build tray menu: {}", e);
+            return None;
+        }
+
+        let icon = match TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_tooltip("Video to MP3")
+            .with_icon(tray_icon(&base, None)?)
+            .build()
+        {
+            Ok(icon) => icon,
+            Err(e) => {
+                println!("Failed to create tray icon: {}", e);
+                return None;
+            }
+        };
+
+        // The handlers run on the event loop, where a hidden window gets no frames, so they show it themselves
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let ctx_menu = ctx.clone();
+        let pending = Arc::clone(&commands);
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            if event.id == "show" {
+                show_window(&ctx_menu);
+            } else if event.id == "pause" {
+                pending.lock().unwrap().push(TrayCommand::TogglePause);
+            } else if event.id == "quit" {
+                pending.lock().unwrap().push(TrayCommand::Quit);
+                show_window(&ctx_menu);
+            }
+            ctx_menu.request_repaint();
+        }));
+        let ctx_click = ctx.clone();
+        TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_window(&ctx_click);
+            }
+        }));
+
+        Some(Self {
+            state: None,
+            icon,
+            base,
+            pause,
+            commands,
+        })
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    pub fn new(_ctx: &egui::Context, _icon: Option<&IconData>) -> Option<Self> {
+        None
+    }
+
+    /// Show `state`, and whether the queue can be paused (`Some(false)`) or resumed (`Some(true)`)
+    ///
+    /// The tray is only touched when something changed.
+    pub fn set(&mut self, state: TrayState, paused: Option<bool>) {
+        if self.state == Some((state, paused)) {
+            return;
+        }
+        self.state = Some((state, paused));
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        self.show(state, paused);
+    }
+
+    /// Menu picks since the last call
+    pub fn commands(&self) -> Vec<TrayCommand> {
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        let commands = std::mem::take(&mut *self.commands.lock().unwrap());
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        let commands = Vec::new();
+        commands
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    fn show(&self, state: TrayState, paused: Option<bool>) {
+        self.pause.set_enabled(paused.is_some());
+        self.pause
+            .set_text(tr(if paused == Some(true) { "Resume queue" } else { "Pause queue" }));
+
+        let (tooltip, badge) = match state {
+            TrayState::Idle => ("Video to MP3".to_string(), None),
+            TrayState::Converting(progress) => (
+                tr_args("Video to MP3 - converting {percent}%", &[("percent", &format!("{:.0}", progress * 100.0))]),
+                Some([96, 165, 250]),
+            ),
+            TrayState::Done => (tr("Video to MP3 - done"), Some([74, 222, 128])),
+        };
+        if let Err(e) = self.icon.set_tooltip(Some(tooltip)) {
+            println!("Failed to update tray tooltip: {}", e);
+        }
+        if let Err(e) = self.icon.set_icon(tray_icon(&self.base, badge)) {
+            println!("Failed to update tray icon: {}", e);
+        }
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn show_window(ctx: &egui::Context) {
+    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    ctx.request_repaint();
+}
+
+/// The app icon, with a dot of `badge` color in the bottom right corner
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn tray_icon(base: &IconData, badge: Option<[u8; 3]>) -> Option<tray_icon::Icon> {
+    let mut rgba = base.rgba.clone();
+    if let Some(color) = badge {
+        let radius = base.width.min(base.height) as f32 * 0.22;
+        let (cx, cy) = (base.width as f32 - radius, base.height as f32 - radius);
+        for y in 0..base.height {
+            for x in 0..base.width {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    let i = ((y * base.width + x) * 4) as usize;
+                    rgba[i..i + 4].copy_from_slice(&[color[0], color[1], color[2], 255]);
+                }
+            }
+        }
+    }
+    tray_icon::Icon::from_rgba(rgba, base.width, base.height).ok()
+}
@@ -0,0 +1,170 @@
+use crate::download::{self, DownloadState};
+use crate::pipeline;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Latest published release of the app
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/hmrnsp/vid2mp3/releases/latest";
+
+/// A release newer than the running app
+#[derive(Clone)]
+pub struct Release {
+    /// Version as tagged, without the leading `v`
+    pub version: String,
+    /// Release page on GitHub
+    pub page: String,
+    /// Installer or archive for this platform, when the release has one
+    pub asset: Option<Asset>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    #[serde(rename = "browser_download_url")]
+    pub url: String,
+}
+
+/// Fields of the GitHub releases API response that are used
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+/// Look for a newer release on a background thread, filling in `found` when there is one
+pub fn check(found: Arc<Mutex<Option<Release>>>) {
+    std::thread::spawn(move || match latest() {
+        Ok(Some(release)) => {
+            println!("Update available: {}", release.version);
+            *found.lock().unwrap() = Some(release);
+        }
+        Ok(None) => {}
+        Err(e) => println!("Update check failed: {}", e),
+    });
+}
+
+/// The latest release, `None` when it isn't newer than this build
+fn latest() -> Result<Option<Release>, String> {
+    // GitHub turns away API requests without a user agent
+    let body = ureq::get(LATEST_RELEASE_URL)
+        .header("User-Agent", concat!("vid2mp3/", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json")
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| e.to_string())?;
+    let release: GithubRelease = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+
+    if !is_newer(&release.tag_name, env!("CARGO_PKG_VERSION")) {
+        return Ok(None);
+    }
+    let version = release.tag_name.trim_start_matches('v').to_string();
+    let asset = release.assets.into_iter().find(|asset| for_this_platform(&asset.name));
+    Ok(Some(Release {
+        version,
+        page: release.html_url,
+        asset,
+    }))
+}
+
+/// Whether `version` comes after `current`, comparing dotted numbers
+///
+/// A leading `v` is ignored and a pre-release such as `1.2.0-beta` comes before `1.2.0`.
+fn is_newer(version: &str, current: &str) -> bool {
+    let key = |v: &str| -> (Vec<u32>, bool) {
+        let v = v.trim().trim_start_matches('v');
+        let (number, pre_release) = match v.split_once('-') {
+            Some((number, _)) => (number, true),
+            None => (v, false),
+        };
+        let mut parts: Vec<u32> = number.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+        // `1.2` and `1.2.0` are the same version
+        while parts.last() == Some(&0) {
+            parts.pop();
+        }
+        (parts, !pre_release)
+    };
+    key(version) > key(current)
+}
+
+/// Whether a release file is meant for the platform the app runs on
+fn for_this_platform(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let hints: &[&str] = match std::env::consts::OS {
+        "windows" => &["windows", "win64", ".exe", ".msi"],
+        "macos" => &["macos", "darwin", ".dmg"],
+        "linux" => &["linux", ".appimage"],
+        _ => &[],
+    };
+    hints.iter().any(|hint| name.contains(hint))
+}
+
+/// Download `asset` into the user's downloads folder on a background thread, reporting through `state`
+///
+/// Nothing is installed, the file is shown in the file manager once it's there.
+pub fn download(asset: Asset, state: Arc<Mutex<DownloadState>>) {
+    *state.lock().unwrap() = DownloadState::Downloading(0.0);
+    std::thread::spawn(move || {
+        let result = fetch(&asset, &state);
+        *state.lock().unwrap() = match result {
+            Ok(path) => {
+                println!("Update downloaded to '{}'", path.display());
+                crate::finish::reveal(&path);
+                DownloadState::Done
+            }
+            Err(e) => {
+                println!("Update download failed: {}", e);
+                DownloadState::Failed(e)
+            }
+        };
+    });
+}
+
+/// Download `asset` next to the user's other downloads, numbered `name (1)` and so on
+/// rather than replacing a file that is already there
+fn fetch(asset: &Asset, state: &Mutex<DownloadState>) -> Result<PathBuf, String> {
+    let dir = directories::UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(PathBuf::from))
+        .ok_or("Could not find the downloads folder")?;
+    // The name comes from the server, only its last component is used
+    let name = Path::new(&asset.name).file_name().ok_or("The download has no file name")?;
+    let free = |path: &Path| !path.exists() && !pipeline::part_path(path).exists();
+    let mut target = dir.join(name);
+    if !free(&target) {
+        target = pipeline::numbered(&target, free);
+    }
+
+    download::fetch(&asset.url, &target, state, |_| {})?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_numbers_are_newer() {
+        assert!(is_newer("1.10.0", "1.9.0"));
+        assert!(is_newer("2.0.0", "1.99.99"));
+        assert!(!is_newer("1.9.0", "1.10.0"));
+        assert!(!is_newer("1.2.0", "1.2.0"));
+        assert!(!is_newer("1.2", "1.2.0"));
+    }
+
+    #[test]
+    fn v_prefix_is_ignored() {
+        assert!(is_newer("v1.3.0", "1.2.0"));
+        assert!(!is_newer("v1.2.0", "1.2.0"));
+        assert!(!is_newer("v1.1.0", "v1.2.0"));
+    }
+
+    #[test]
+    fn pre_releases_come_before_their_release() {
+        assert!(!is_newer("1.3.0-beta", "1.3.0"));
+        assert!(is_newer("1.3.0", "1.3.0-beta"));
+        assert!(is_newer("1.3.0-beta.1", "1.2.0"));
+        assert!(!is_newer("v1.2.0-rc1", "1.2.0"));
+    }
+}